@@ -1,59 +1,40 @@
+#[path = "build/schema.rs"]
+mod schema;
+
 use quote::quote;
-use serde::Deserialize;
+use schema::*;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
 
-#[derive(Debug, Deserialize)]
-struct SchemaField {
-    name: String,
-    #[serde(rename = "type")]
-    type_name: String,
-}
-
-#[derive(Debug, Deserialize)]
-struct SchemaNode {
-    name: String,
-    fields: Vec<SchemaField>,
-}
-
-#[derive(Debug, Deserialize)]
-struct SchemaConnection {
-    from: String,
-    to: String,
-    name: String,
-}
-
-#[derive(Debug, Deserialize)]
-struct SchemaEdge {
-    name: String,
-    connections: Vec<SchemaConnection>,
-    fields: Vec<SchemaField>,
-}
-
-#[derive(Debug, Deserialize)]
-struct Schema {
-    nodes: Vec<SchemaNode>,
-    edges: Vec<SchemaEdge>,
-}
-
 fn main() {
     let schema: Schema = serde_yaml::from_reader(File::open("schema.yml").unwrap()).unwrap();
+    let errors = schema_errors(&schema);
+    if !errors.is_empty() {
+        panic!("invalid schema.yml:\n  {}", errors.join("\n  "));
+    }
     let mut output = File::create("src/generated.rs").unwrap();
     let mut families: Vec<String> = Vec::new();
+    let mut node_families: Vec<String> = Vec::new();
+    let mut edge_families: Vec<String> = Vec::new();
+    let mut undirected_edge_families: Vec<String> = Vec::new();
+    let mut index_families: Vec<String> = Vec::new();
+    let mut indexed_field_arms = Vec::new();
+    let mut range_indexed_field_arms = Vec::new();
+    let mut prefix_length_arms = Vec::new();
+    let mut any_node_variants = Vec::new();
+    let mut any_edge_variants = Vec::new();
     let mut node_edge_types: HashMap<String, (Vec<String>, Vec<String>)> = HashMap::new();
 
     let imports_impl = quote! {
         use serde::{Serialize, Deserialize};
-        use xid;
     };
 
     writeln!(output, "{}", imports_impl).unwrap();
 
     let node_impl = quote! {
-        pub trait NodeId: Serialize + for<'de> Deserialize<'de> + Clone + std::fmt::Debug {
+        pub trait NodeId: Serialize + for<'de> Deserialize<'de> + Clone + std::fmt::Debug + std::fmt::Display {
             fn new(id: Option<String>) -> Self;
-            fn to_string(&self) -> String;
             fn family_name(&self) -> String;
         }
 
@@ -65,6 +46,9 @@ fn main() {
             type ValidInEdgeId: NodeValidInEdgeId;
             type ValidOutEdgeId: NodeValidOutEdgeId;
 
+            const FAMILY: &'static str;
+            const FIELDS: &'static [&'static str];
+
             fn id(&self) -> &Self::Id;
             fn in_edge_ids(&self) -> Vec<Self::ValidInEdgeId>;
             fn out_edge_ids(&self) -> Vec<Self::ValidOutEdgeId>;
@@ -73,24 +57,41 @@ fn main() {
             fn add_out_edge_id(&mut self, edge_id: Self::ValidOutEdgeId);
             fn remove_out_edge_id(&mut self, edge_id: Self::ValidOutEdgeId);
             fn family_name(&self) -> String;
+            fn sort_key(&self) -> String;
+            fn created_at(&self) -> Option<i64>;
+            fn updated_at(&self) -> Option<i64>;
+            fn touch(&mut self);
+
+            fn family_name_static() -> &'static str {
+                Self::FAMILY
+            }
         }
     };
 
     let edge_impl = quote! {
-        pub trait EdgeId: Serialize + for<'de> Deserialize<'de> + Clone + std::fmt::Debug {
-            fn to_string(&self) -> String;
+        pub trait EdgeId: Serialize + for<'de> Deserialize<'de> + Clone + std::fmt::Debug + std::fmt::Display {
             fn family_name(&self) -> String;
         }
 
-        pub trait EdgeConnection: Serialize + for<'de> Deserialize<'de> + Clone + std::fmt::Debug {}
+        pub trait EdgeConnection: Serialize + for<'de> Deserialize<'de> + Clone + std::fmt::Debug {
+            fn endpoints(&self) -> (String, String);
+            fn validate(&self) -> Result<(), String>;
+        }
 
         pub trait Edge: Serialize + for<'de> Deserialize<'de> + Clone + std::fmt::Debug {
             type Id: EdgeId;
             type Connection: EdgeConnection;
 
+            const FAMILY: &'static str;
+
             fn id(&self) -> &Self::Id;
             fn connection(&self) -> &Self::Connection;
+            fn created_at(&self) -> Option<i64>;
             fn family_name(&self) -> String;
+
+            fn family_name_static() -> &'static str {
+                Self::FAMILY
+            }
         }
     };
 
@@ -107,19 +108,24 @@ fn main() {
         );
 
         families.push(struct_name.to_string());
+        edge_families.push(struct_name.to_string());
+        if edge.undirected {
+            undirected_edge_families.push(struct_name.to_string());
+        }
+        any_edge_variants.push(struct_name.clone());
 
         let mut field_idents = Vec::new();
         let mut field_types = Vec::new();
 
         for field in &edge.fields {
             field_idents.push(syn::Ident::new(&field.name, proc_macro2::Span::call_site()));
-            field_types.push(syn::Ident::new(
-                &field.type_name,
-                proc_macro2::Span::call_site(),
-            ));
+            field_types.push(syn::parse_str::<syn::Type>(&field.type_name).unwrap());
         }
 
         let mut connection_variants = Vec::new();
+        let mut connection_names = Vec::new();
+        let mut connection_from_prefixes = Vec::new();
+        let mut connection_to_prefixes = Vec::new();
 
         for connection in &edge.connections {
             let edge_name = &edge.name;
@@ -129,6 +135,9 @@ fn main() {
             if !entry_from.1.contains(edge_name) {
                 entry_from.1.push(edge_name.clone());
             }
+            if edge.undirected && !entry_from.0.contains(edge_name) {
+                entry_from.0.push(edge_name.clone());
+            }
 
             let entry_to = node_edge_types
                 .entry(connection.to.clone())
@@ -136,6 +145,9 @@ fn main() {
             if !entry_to.0.contains(edge_name) {
                 entry_to.0.push(edge_name.clone());
             }
+            if edge.undirected && !entry_to.1.contains(edge_name) {
+                entry_to.1.push(edge_name.clone());
+            }
 
             let from = syn::Ident::new(
                 &format!("{}Id", &connection.from),
@@ -149,41 +161,95 @@ fn main() {
             let connection_variant =
                 syn::Ident::new(&connection.name, proc_macro2::Span::call_site());
             connection_variants.push(quote! { #connection_variant(#from, #to) });
+            connection_names.push(connection_variant);
+            connection_from_prefixes.push(format!("{}:", connection.from));
+            connection_to_prefixes.push(format!("{}:", connection.to));
         }
 
+        let (timestamp_field, timestamp_init, created_at) = if edge.timestamps {
+            (
+                quote! { #[serde(default)] created_at: i64, },
+                quote! { created_at: crate::timestamp_millis(), },
+                quote! { Some(self.created_at) },
+            )
+        } else {
+            (quote! {}, quote! {}, quote! { None })
+        };
+
+        let edge_hash_derives = if edge
+            .fields
+            .iter()
+            .all(|field| is_hashable(&field.type_name))
+        {
+            quote! { Eq, Hash }
+        } else {
+            quote! {}
+        };
+
         let edge_impl = quote! {
-            #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+            #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
             pub struct #struct_name_id(String);
 
             impl EdgeId for #struct_name_id {
-                fn to_string(&self) -> String {
-                    self.0.clone()
-                }
-
                 fn family_name(&self) -> String {
                     stringify!(#struct_name).to_string()
                 }
             }
 
-            #[derive(Debug, Serialize, Deserialize, Clone)]
+            impl std::fmt::Display for #struct_name_id {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    f.write_str(&self.0)
+                }
+            }
+
+            impl std::str::FromStr for #struct_name_id {
+                type Err = crate::GraphError;
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    crate::check_id_family(s, stringify!(#struct_name))?;
+                    Ok(Self(s.to_string()))
+                }
+            }
+
+            #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
             pub enum #struct_name_connection {
                     #( #connection_variants ),*
             }
 
-            impl EdgeConnection for #struct_name_connection {}
+            impl EdgeConnection for #struct_name_connection {
+                fn endpoints(&self) -> (String, String) {
+                    match self {
+                        #( Self::#connection_names(from, to) => (from.to_string(), to.to_string()), )*
+                    }
+                }
+
+                fn validate(&self) -> Result<(), String> {
+                    let (from, to) = self.endpoints();
+                    let (from_prefix, to_prefix) = match self {
+                        #( Self::#connection_names(..) => (#connection_from_prefixes, #connection_to_prefixes), )*
+                    };
+                    match (from.starts_with(from_prefix), to.starts_with(to_prefix)) {
+                        (false, _) => Err(from),
+                        (_, false) => Err(to),
+                        _ => Ok(()),
+                    }
+                }
+            }
 
-            #[derive(Debug, Serialize, Deserialize, Clone)]
+            #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, #edge_hash_derives)]
             pub struct #struct_name {
                 id: #struct_name_id,
                 connection: #struct_name_connection,
                 #( #field_idents: #field_types, )*
+                #timestamp_field
             }
 
             impl #struct_name {
                 pub fn new(id: Option<String>, connection: #struct_name_connection, #( #field_idents: #field_types, )*) -> Self {
                     Self {
-                        id: #struct_name_id(format!(concat!(stringify!(#struct_name), ":{}"), id.unwrap_or_else(|| xid::new().to_string()))),
+                        id: #struct_name_id(format!(concat!(stringify!(#struct_name), ":{}"), id.unwrap_or_else(crate::generate_id))),
                         connection,
+                        #timestamp_init
                         #( #field_idents ),*
                     }
                 }
@@ -191,6 +257,21 @@ fn main() {
                 pub fn id(&self) -> &#struct_name_id {
                     &self.id
                 }
+
+                /// Decodes the msgpack form the graph stores. `FromStr` reads JSON.
+                pub fn from_msgpack(bytes: &[u8]) -> Result<Self, rmp_serde::decode::Error> {
+                    rmp_serde::from_slice(bytes)
+                }
+
+                pub fn to_msgpack(&self) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+                    rmp_serde::to_vec_named(self)
+                }
+
+                #(
+                    pub fn #field_idents(&self) -> &#field_types {
+                        &self.#field_idents
+                    }
+                )*
             }
 
             impl std::str::FromStr for #struct_name {
@@ -205,6 +286,8 @@ fn main() {
                 type Id = #struct_name_id;
                 type Connection = #struct_name_connection;
 
+                const FAMILY: &'static str = stringify!(#struct_name);
+
                 fn id(&self) -> &Self::Id {
                     &self.id
                 }
@@ -213,8 +296,12 @@ fn main() {
                     &self.connection
                 }
 
+                fn created_at(&self) -> Option<i64> {
+                    #created_at
+                }
+
                 fn family_name(&self) -> String {
-                    stringify!(#struct_name).to_string()
+                    Self::FAMILY.to_string()
                 }
             }
         };
@@ -234,37 +321,196 @@ fn main() {
             &format!("{}OutEdge", &node.name),
             proc_macro2::Span::call_site(),
         );
+        let struct_name_builder = syn::Ident::new(
+            &format!("{}Builder", &node.name),
+            proc_macro2::Span::call_site(),
+        );
+
+        families.push(struct_name.to_string());
+        node_families.push(struct_name.to_string());
+        any_node_variants.push(struct_name.clone());
+
+        if let Some(id_length) = node.prefix_extractor {
+            let node_name = &node.name;
+            let prefix_length =
+                proc_macro2::Literal::usize_unsuffixed(node.name.len() + 1 + id_length);
+            prefix_length_arms.push(quote! { #node_name => Some(#prefix_length), });
+        }
+
+        let indexed_field_names: Vec<&str> = node
+            .fields
+            .iter()
+            .filter(|field| field.indexed)
+            .map(|field| field.name.as_str())
+            .collect();
+        for field_name in &indexed_field_names {
+            let index_family = format!("{}_by_{}", &node.name, field_name);
+            families.push(index_family.clone());
+            index_families.push(index_family);
+        }
+        if !indexed_field_names.is_empty() {
+            let node_name = &node.name;
+            indexed_field_arms.push(quote! { #node_name => &[#( #indexed_field_names ),*], });
+        }
+
+        let range_indexed_field_names: Vec<&str> = node
+            .fields
+            .iter()
+            .filter(|field| field.indexed && is_numeric(&field.type_name))
+            .map(|field| field.name.as_str())
+            .collect();
+        if !range_indexed_field_names.is_empty() {
+            let node_name = &node.name;
+            range_indexed_field_arms
+                .push(quote! { #node_name => &[#( #range_indexed_field_names ),*], });
+        }
 
         let mut field_idents = Vec::new();
         let mut field_types = Vec::new();
+        let mut field_getters = Vec::new();
+        let mut field_defaults = Vec::new();
+        let mut field_attrs = Vec::new();
+        let mut default_fns = Vec::new();
+        let mut builder_types = Vec::new();
+        let mut builder_values = Vec::new();
+        let mut builder_setters = Vec::new();
         for field in &node.fields {
-            field_idents.push(syn::Ident::new(&field.name, proc_macro2::Span::call_site()));
-            field_types.push(syn::Ident::new(
-                &field.type_name,
+            let field_ident = syn::Ident::new(&field.name, proc_macro2::Span::call_site());
+            let field_type = syn::parse_str::<syn::Type>(&field.type_name).unwrap();
+
+            if is_byte_blob(&field.type_name) {
+                field_getters.push(quote! {
+                    pub fn #field_ident(&self) -> &[u8] {
+                        &self.#field_ident
+                    }
+                });
+            } else {
+                field_getters.push(quote! {
+                    pub fn #field_ident(&self) -> &#field_type {
+                        &self.#field_ident
+                    }
+                });
+            }
+
+            let field_default = field
+                .default
+                .as_ref()
+                .map(|default| syn::parse_str::<syn::Expr>(default).unwrap());
+            let field_name = &field.name;
+            let optional_type = option_inner(&field.type_name)
+                .map(|inner| syn::parse_str::<syn::Type>(inner).unwrap());
+            match &field_default {
+                Some(default) => {
+                    let default_fn_name =
+                        format!("default_{}_{}", node.name.to_lowercase(), field.name);
+                    let default_fn =
+                        syn::Ident::new(&default_fn_name, proc_macro2::Span::call_site());
+                    default_fns.push(quote! {
+                        fn #default_fn() -> #field_type {
+                            #default
+                        }
+                    });
+                    field_attrs.push(quote! { #[serde(default = #default_fn_name)] });
+                    builder_values.push(match &optional_type {
+                        Some(_) => {
+                            quote! { self.#field_ident.map(Some).unwrap_or_else(#default_fn) }
+                        }
+                        None => quote! { self.#field_ident.unwrap_or_else(#default_fn) },
+                    });
+                }
+                None => {
+                    field_attrs.push(quote! {});
+                    builder_values.push(match &optional_type {
+                        Some(_) => quote! { self.#field_ident },
+                        None => quote! {
+                            self.#field_ident.ok_or_else(|| crate::GraphError::MissingFieldError(#field_name.to_string()))?
+                        },
+                    });
+                }
+            }
+            builder_types.push(optional_type.unwrap_or_else(|| field_type.clone()));
+            builder_setters.push(syn::Ident::new(
+                &format!("with_{}", field.name),
                 proc_macro2::Span::call_site(),
             ));
+            field_defaults.push(field_default);
+            field_idents.push(field_ident);
+            field_types.push(field_type);
         }
 
-        let (in_edge_types, out_edge_types) = node_edge_types.get(&node.name).unwrap();
+        let sort_key = match &node.sort_by {
+            Some(sort_by) => {
+                let sort_by = syn::Ident::new(sort_by, proc_macro2::Span::call_site());
+                quote! { self.#sort_by.to_string() }
+            }
+            None => quote! { self.id.0.clone() },
+        };
+
+        let (timestamp_fields, timestamp_init, created_at, updated_at, touch) = if node.timestamps {
+            (
+                quote! { #[serde(default)] created_at: i64, #[serde(default)] updated_at: i64, },
+                quote! { created_at: crate::timestamp_millis(), updated_at: crate::timestamp_millis(), },
+                quote! { Some(self.created_at) },
+                quote! { Some(self.updated_at) },
+                quote! { self.updated_at = crate::timestamp_millis(); },
+            )
+        } else {
+            (
+                quote! {},
+                quote! {},
+                quote! { None },
+                quote! { None },
+                quote! {},
+            )
+        };
+
+        let default_impl = match field_defaults.into_iter().collect::<Option<Vec<_>>>() {
+            Some(field_defaults) => quote! {
+                impl Default for #struct_name {
+                    fn default() -> Self {
+                        Self {
+                            id: #struct_name_id::new(None),
+                            in_edge_ids: Vec::new(),
+                            out_edge_ids: Vec::new(),
+                            #( #field_idents: #field_defaults, )*
+                            #timestamp_init
+                        }
+                    }
+                }
+            },
+            None => quote! {},
+        };
+
+        // Nodes without incident edges get empty in/out enums.
+        let (in_edge_types, out_edge_types) =
+            node_edge_types.get(&node.name).cloned().unwrap_or_default();
 
         let in_edge_variants = in_edge_types
             .iter()
-            .map(|edge| syn::Ident::new(&format!("{}Id", edge), proc_macro2::Span::call_site()));
+            .map(|edge| syn::Ident::new(&format!("{}Id", edge), proc_macro2::Span::call_site()))
+            .collect::<Vec<_>>();
         let out_edge_variants = out_edge_types
             .iter()
-            .map(|edge| syn::Ident::new(&format!("{}Id", edge), proc_macro2::Span::call_site()));
+            .map(|edge| syn::Ident::new(&format!("{}Id", edge), proc_macro2::Span::call_site()))
+            .collect::<Vec<_>>();
+
+        let node_hash_derives = if node
+            .fields
+            .iter()
+            .all(|field| is_hashable(&field.type_name))
+        {
+            quote! { Eq, Hash }
+        } else {
+            quote! {}
+        };
 
         let node_impl = quote! {
-            #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+            #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
             pub struct #struct_name_id(String);
 
             impl NodeId for #struct_name_id {
                 fn new (id: Option<String>) -> Self {
-                    Self(format!(concat!(stringify!(#struct_name), ":{}"), id.unwrap_or_else(|| xid::new().to_string())))
-                }
-
-                fn to_string(&self) -> String {
-                    self.0.clone()
+                    Self(format!(concat!(stringify!(#struct_name), ":{}"), id.unwrap_or_else(crate::generate_id)))
                 }
 
                 fn family_name(&self) -> String {
@@ -272,43 +518,123 @@ fn main() {
                 }
             }
 
-            #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+            impl std::fmt::Display for #struct_name_id {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    f.write_str(&self.0)
+                }
+            }
+
+            impl std::str::FromStr for #struct_name_id {
+                type Err = crate::GraphError;
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    crate::check_id_family(s, stringify!(#struct_name))?;
+                    Ok(Self(s.to_string()))
+                }
+            }
+
+            #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
             pub enum #struct_name_in_edge_ident {
                 #( #in_edge_variants(#in_edge_variants), )*
             }
 
             impl NodeValidInEdgeId for #struct_name_in_edge_ident {}
 
-            #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+            #(
+                impl From<#in_edge_variants> for #struct_name_in_edge_ident {
+                    fn from(edge_id: #in_edge_variants) -> Self {
+                        Self::#in_edge_variants(edge_id)
+                    }
+                }
+            )*
+
+            #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
             pub enum #struct_name_out_edge_ident {
                 #( #out_edge_variants(#out_edge_variants), )*
             }
 
             impl NodeValidOutEdgeId for #struct_name_out_edge_ident {}
 
-            #[derive(Debug, Serialize, Deserialize, Clone)]
+            #(
+                impl From<#out_edge_variants> for #struct_name_out_edge_ident {
+                    fn from(edge_id: #out_edge_variants) -> Self {
+                        Self::#out_edge_variants(edge_id)
+                    }
+                }
+            )*
+
+            #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, #node_hash_derives)]
             pub struct #struct_name {
                 id: #struct_name_id,
                 in_edge_ids: Vec<#struct_name_in_edge_ident>,
                 out_edge_ids: Vec<#struct_name_out_edge_ident>,
-                #( #field_idents: #field_types, )*
+                #( #field_attrs #field_idents: #field_types, )*
+                #timestamp_fields
             }
 
+            #( #default_fns )*
+
             impl #struct_name {
                 pub fn new(id: Option<String>, #( #field_idents: #field_types, )*) -> Self {
                     Self {
-                        id: #struct_name_id(format!(concat!(stringify!(#struct_name), ":{}"), id.unwrap_or(xid::new().to_string()))),
+                        id: #struct_name_id(format!(concat!(stringify!(#struct_name), ":{}"), id.unwrap_or_else(crate::generate_id))),
                         in_edge_ids: Vec::new(),
                         out_edge_ids: Vec::new(),
-                        #( #field_idents ),*,
+                        #timestamp_init
+                        #( #field_idents, )*
                     }
                 }
 
                 pub fn new_id(id: String) -> #struct_name_id {
                     #struct_name_id(format!(concat!(stringify!(#struct_name), ":{}"), id))
                 }
+
+                pub fn field_names() -> &'static [&'static str] {
+                    <Self as Node>::FIELDS
+                }
+
+                pub fn builder() -> #struct_name_builder {
+                    #struct_name_builder::default()
+                }
+
+                /// Decodes the msgpack form the graph stores. `FromStr` reads JSON.
+                pub fn from_msgpack(bytes: &[u8]) -> Result<Self, rmp_serde::decode::Error> {
+                    rmp_serde::from_slice(bytes)
+                }
+
+                pub fn to_msgpack(&self) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+                    rmp_serde::to_vec_named(self)
+                }
+
+                #( #field_getters )*
+            }
+
+            #[derive(Debug, Clone, Default)]
+            pub struct #struct_name_builder {
+                id: Option<String>,
+                #( #field_idents: Option<#builder_types>, )*
+            }
+
+            impl #struct_name_builder {
+                pub fn with_id(mut self, id: String) -> Self {
+                    self.id = Some(id);
+                    self
+                }
+
+                #(
+                    pub fn #builder_setters(mut self, #field_idents: #builder_types) -> Self {
+                        self.#field_idents = Some(#field_idents);
+                        self
+                    }
+                )*
+
+                pub fn build(self) -> Result<#struct_name, crate::GraphError> {
+                    Ok(#struct_name::new(self.id, #( #builder_values, )*))
+                }
             }
 
+            #default_impl
+
             impl std::str::FromStr for #struct_name {
                 type Err = serde_json::Error;
 
@@ -322,6 +648,9 @@ fn main() {
                 type ValidInEdgeId = #struct_name_in_edge_ident;
                 type ValidOutEdgeId = #struct_name_out_edge_ident;
 
+                const FAMILY: &'static str = stringify!(#struct_name);
+                const FIELDS: &'static [&'static str] = &[#( stringify!(#field_idents) ),*];
+
                 fn id(&self) -> &#struct_name_id {
                     &self.id
                 }
@@ -351,7 +680,23 @@ fn main() {
                 }
 
                 fn family_name(&self) -> String {
-                    stringify!(#struct_name).to_string()
+                    Self::FAMILY.to_string()
+                }
+
+                fn sort_key(&self) -> String {
+                    #sort_key
+                }
+
+                fn created_at(&self) -> Option<i64> {
+                    #created_at
+                }
+
+                fn updated_at(&self) -> Option<i64> {
+                    #updated_at
+                }
+
+                fn touch(&mut self) {
+                    #touch
                 }
             }
         };
@@ -359,11 +704,151 @@ fn main() {
         writeln!(output, "{}", node_impl).unwrap();
     }
 
+    let any_node_impl = quote! {
+        #[derive(Debug, Clone)]
+        pub enum AnyNode {
+            #( #any_node_variants(#any_node_variants) ),*
+        }
+
+        impl AnyNode {
+            pub fn decode<C: crate::Codec>(codec: &C, family: &str, bytes: &[u8]) -> Option<Result<Self, crate::GraphError>> {
+                match family {
+                    #( #node_families => Some(codec.decode(bytes).map(Self::#any_node_variants)), )*
+                    _ => None,
+                }
+            }
+
+            pub fn id(&self) -> String {
+                match self {
+                    #( Self::#any_node_variants(node) => node.id().to_string(), )*
+                }
+            }
+
+            pub fn family_name(&self) -> &'static str {
+                match self {
+                    #( Self::#any_node_variants(_) => <#any_node_variants as Node>::FAMILY, )*
+                }
+            }
+
+            pub fn in_degree(&self) -> usize {
+                match self {
+                    #( Self::#any_node_variants(node) => node.in_edge_ids.len(), )*
+                }
+            }
+
+            pub fn out_degree(&self) -> usize {
+                match self {
+                    #( Self::#any_node_variants(node) => node.out_edge_ids.len(), )*
+                }
+            }
+        }
+
+        #(
+            impl From<#any_node_variants> for AnyNode {
+                fn from(node: #any_node_variants) -> Self {
+                    Self::#any_node_variants(node)
+                }
+            }
+        )*
+    };
+
+    writeln!(output, "{}", any_node_impl).unwrap();
+
+    let any_edge_impl = quote! {
+        #[derive(Debug, Clone)]
+        pub enum AnyEdge {
+            #( #any_edge_variants(#any_edge_variants) ),*
+        }
+
+        impl AnyEdge {
+            pub fn decode<C: crate::Codec>(codec: &C, family: &str, bytes: &[u8]) -> Option<Result<Self, crate::GraphError>> {
+                match family {
+                    #( #edge_families => Some(codec.decode(bytes).map(Self::#any_edge_variants)), )*
+                    _ => None,
+                }
+            }
+
+            pub fn id(&self) -> String {
+                match self {
+                    #( Self::#any_edge_variants(edge) => edge.id().to_string(), )*
+                }
+            }
+
+            pub fn family_name(&self) -> &'static str {
+                match self {
+                    #( Self::#any_edge_variants(_) => <#any_edge_variants as Edge>::FAMILY, )*
+                }
+            }
+
+            pub fn endpoints(&self) -> (String, String) {
+                match self {
+                    #( Self::#any_edge_variants(edge) => edge.connection().endpoints(), )*
+                }
+            }
+        }
+
+        #(
+            impl From<#any_edge_variants> for AnyEdge {
+                fn from(edge: #any_edge_variants) -> Self {
+                    Self::#any_edge_variants(edge)
+                }
+            }
+        )*
+    };
+
+    writeln!(output, "{}", any_edge_impl).unwrap();
+
     let families_impl = quote! {
         pub fn families() -> Vec<&'static str> {
         vec![#( #families ),*]
         }
+
+        pub fn node_families() -> Vec<&'static str> {
+        vec![#( #node_families ),*]
+        }
+
+        pub fn edge_families() -> Vec<&'static str> {
+        vec![#( #edge_families ),*]
+        }
+
+        pub fn undirected_edge_families() -> Vec<&'static str> {
+        vec![#( #undirected_edge_families ),*]
+        }
+
+        pub fn index_families() -> Vec<&'static str> {
+        vec![#( #index_families ),*]
+        }
+
+        pub fn indexed_fields(family: &str) -> &'static [&'static str] {
+            match family {
+                #( #indexed_field_arms )*
+                _ => &[],
+            }
+        }
+
+        pub fn range_indexed_fields(family: &str) -> &'static [&'static str] {
+            match family {
+                #( #range_indexed_field_arms )*
+                _ => &[],
+            }
+        }
+
+        pub fn family_prefix_length(family: &str) -> Option<usize> {
+            match family {
+                #( #prefix_length_arms )*
+                _ => None,
+            }
+        }
     };
 
     writeln!(output, "{}", families_impl).unwrap();
+
+    let schema_json = serde_json::to_string(&schema).unwrap();
+    let schema_json_impl = quote! {
+        pub fn schema_json() -> &'static str {
+            #schema_json
+        }
+    };
+
+    writeln!(output, "{}", schema_json_impl).unwrap();
 }
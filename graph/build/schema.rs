@@ -0,0 +1,162 @@
+//! The schema.yml model and the checks run on it before any code is
+//! generated. Kept apart from `build.rs` so the checks can be tested.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SchemaField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_name: String,
+    #[serde(default)]
+    pub default: Option<String>,
+    #[serde(default)]
+    pub indexed: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SchemaNode {
+    pub name: String,
+    #[serde(default)]
+    pub fields: Vec<SchemaField>,
+    #[serde(default)]
+    pub sort_by: Option<String>,
+    /// How many characters of the id after `Family:` the family's prefix
+    /// extractor keeps. A prefix extractor only pays off when keys sharing a
+    /// prefix are read together, such as `nodes_with_id_prefix` seeks; a
+    /// prefix of just `Family:` matches every key in the family.
+    #[serde(default)]
+    pub prefix_extractor: Option<usize>,
+    #[serde(default)]
+    pub timestamps: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SchemaConnection {
+    pub from: String,
+    pub to: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SchemaEdge {
+    pub name: String,
+    pub connections: Vec<SchemaConnection>,
+    #[serde(default)]
+    pub fields: Vec<SchemaField>,
+    #[serde(default)]
+    pub timestamps: bool,
+    #[serde(default)]
+    pub undirected: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Schema {
+    pub nodes: Vec<SchemaNode>,
+    pub edges: Vec<SchemaEdge>,
+}
+
+pub fn is_byte_blob(type_name: &str) -> bool {
+    type_name.replace(' ', "") == "Vec<u8>"
+}
+
+pub fn option_inner(type_name: &str) -> Option<&str> {
+    type_name
+        .trim()
+        .strip_prefix("Option<")
+        .and_then(|inner| inner.strip_suffix('>'))
+        .map(str::trim)
+}
+
+pub fn is_numeric(type_name: &str) -> bool {
+    matches!(
+        type_name.trim(),
+        "i8" | "i16"
+            | "i32"
+            | "i64"
+            | "isize"
+            | "u8"
+            | "u16"
+            | "u32"
+            | "u64"
+            | "usize"
+            | "f32"
+            | "f64"
+    )
+}
+
+pub fn is_displayable(type_name: &str) -> bool {
+    matches!(type_name.trim(), "String" | "bool" | "char") || is_numeric(type_name)
+}
+
+/// Whether a field type can take part in derived `Eq`/`Hash`. Floats and
+/// hashed collections can't.
+pub fn is_hashable(type_name: &str) -> bool {
+    !type_name
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .any(|part| matches!(part, "f32" | "f64" | "HashMap" | "HashSet"))
+}
+
+/// Every problem with the schema that would otherwise surface as a panic or a
+/// confusing error in the generated code.
+pub fn schema_errors(schema: &Schema) -> Vec<String> {
+    let node_names: Vec<&str> = schema.nodes.iter().map(|node| node.name.as_str()).collect();
+    let mut errors = Vec::new();
+    let mut families: HashMap<&str, &str> = HashMap::new();
+    let declared = schema
+        .nodes
+        .iter()
+        .map(|node| ("node", node.name.as_str()))
+        .chain(schema.edges.iter().map(|edge| ("edge", edge.name.as_str())));
+    for (kind, name) in declared {
+        if let Some(previous) = families.insert(name, kind) {
+            errors.push(format!(
+                "{} `{}` reuses the name of an earlier {}",
+                kind, name, previous
+            ));
+        }
+    }
+    for node in &schema.nodes {
+        if let Some(sort_by) = &node.sort_by {
+            match node.fields.iter().find(|field| &field.name == sort_by) {
+                None => errors.push(format!(
+                    "node `{}` sorts by unknown field `{}`",
+                    node.name, sort_by
+                )),
+                Some(field) if !is_displayable(&field.type_name) => errors.push(format!(
+                    "node `{}` sorts by field `{}` of type `{}`, which has no string form",
+                    node.name, sort_by, field.type_name
+                )),
+                Some(_) => {}
+            }
+        }
+        if node.prefix_extractor == Some(0) {
+            errors.push(format!(
+                "node `{}` has a prefix_extractor of 0, which matches every key",
+                node.name
+            ));
+        }
+    }
+    for edge in &schema.edges {
+        let mut connection_names = Vec::new();
+        for connection in &edge.connections {
+            if connection_names.contains(&connection.name.as_str()) {
+                errors.push(format!(
+                    "edge `{}` declares connection `{}` more than once",
+                    edge.name, connection.name
+                ));
+            }
+            connection_names.push(connection.name.as_str());
+            for endpoint in [&connection.from, &connection.to] {
+                if !node_names.contains(&endpoint.as_str()) {
+                    errors.push(format!(
+                        "edge `{}` connection `{}` references unknown node `{}`",
+                        edge.name, connection.name, endpoint
+                    ));
+                }
+            }
+        }
+    }
+    errors
+}
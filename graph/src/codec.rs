@@ -0,0 +1,111 @@
+use serde::{de::DeserializeOwned, de::DeserializeSeed, Deserialize, Serialize};
+
+use crate::GraphError;
+
+/// How records are encoded in their column families. A database must be
+/// opened with the codec it was written with.
+pub trait Codec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, GraphError>;
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, GraphError>;
+    fn decode_seed<'de, S: DeserializeSeed<'de>>(
+        &self,
+        seed: S,
+        bytes: &'de [u8],
+    ) -> Result<S::Value, GraphError>;
+}
+
+/// Compact msgpack with named fields. The default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MsgpackCodec;
+
+/// Human-readable JSON, useful when inspecting a database by hand.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JsonCodec;
+
+/// The codec a `Graph` stores records with. The format a graph is created
+/// with is recorded in `__meta__`, so a restored backup can be reopened with it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StorageFormat {
+    #[default]
+    Msgpack,
+    Json,
+}
+
+impl Codec for MsgpackCodec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, GraphError> {
+        Ok(rmp_serde::to_vec_named(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, GraphError> {
+        Ok(rmp_serde::from_slice(bytes)?)
+    }
+
+    fn decode_seed<'de, S: DeserializeSeed<'de>>(
+        &self,
+        seed: S,
+        bytes: &'de [u8],
+    ) -> Result<S::Value, GraphError> {
+        let mut deserializer = rmp_serde::Deserializer::new(bytes);
+        Ok(seed.deserialize(&mut deserializer)?)
+    }
+}
+
+impl Codec for JsonCodec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, GraphError> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, GraphError> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+
+    fn decode_seed<'de, S: DeserializeSeed<'de>>(
+        &self,
+        seed: S,
+        bytes: &'de [u8],
+    ) -> Result<S::Value, GraphError> {
+        let mut deserializer = serde_json::Deserializer::from_slice(bytes);
+        let value = seed.deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(value)
+    }
+}
+
+impl Codec for StorageFormat {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, GraphError> {
+        match self {
+            StorageFormat::Msgpack => MsgpackCodec.encode(value),
+            StorageFormat::Json => JsonCodec.encode(value),
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, GraphError> {
+        match self {
+            StorageFormat::Msgpack => MsgpackCodec.decode(bytes),
+            StorageFormat::Json => JsonCodec.decode(bytes),
+        }
+    }
+
+    fn decode_seed<'de, S: DeserializeSeed<'de>>(
+        &self,
+        seed: S,
+        bytes: &'de [u8],
+    ) -> Result<S::Value, GraphError> {
+        match self {
+            StorageFormat::Msgpack => MsgpackCodec.decode_seed(seed, bytes),
+            StorageFormat::Json => JsonCodec.decode_seed(seed, bytes),
+        }
+    }
+}
+
+impl From<MsgpackCodec> for StorageFormat {
+    fn from(_: MsgpackCodec) -> Self {
+        StorageFormat::Msgpack
+    }
+}
+
+impl From<JsonCodec> for StorageFormat {
+    fn from(_: JsonCodec) -> Self {
+        StorageFormat::Json
+    }
+}
@@ -1,8 +1,14 @@
-use serde :: { Serialize , Deserialize } ; use xid ;
-pub trait NodeId : Serialize + for < 'de > Deserialize < 'de > + Clone + std :: fmt :: Debug { fn new (id : Option < String >) -> Self ; fn to_string (& self) -> String ; fn family_name (& self) -> String ; } pub trait NodeValidInEdgeId : Serialize + for < 'de > Deserialize < 'de > + Clone + std :: fmt :: Debug { } pub trait NodeValidOutEdgeId : Serialize + for < 'de > Deserialize < 'de > + Clone + std :: fmt :: Debug { } pub trait Node : Serialize + for < 'de > Deserialize < 'de > + Clone + std :: fmt :: Debug { type Id : NodeId ; type ValidInEdgeId : NodeValidInEdgeId ; type ValidOutEdgeId : NodeValidOutEdgeId ; fn id (& self) -> & Self :: Id ; fn in_edge_ids (& self) -> Vec < Self :: ValidInEdgeId > ; fn out_edge_ids (& self) -> Vec < Self :: ValidOutEdgeId > ; fn add_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) ; fn remove_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) ; fn add_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) ; fn remove_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) ; fn family_name (& self) -> String ; }
-pub trait EdgeId : Serialize + for < 'de > Deserialize < 'de > + Clone + std :: fmt :: Debug { fn to_string (& self) -> String ; fn family_name (& self) -> String ; } pub trait EdgeConnection : Serialize + for < 'de > Deserialize < 'de > + Clone + std :: fmt :: Debug { } pub trait Edge : Serialize + for < 'de > Deserialize < 'de > + Clone + std :: fmt :: Debug { type Id : EdgeId ; type Connection : EdgeConnection ; fn id (& self) -> & Self :: Id ; fn connection (& self) -> & Self :: Connection ; fn family_name (& self) -> String ; }
-# [derive (Debug , Serialize , Deserialize , Clone , PartialEq)] pub struct ById (String) ; impl EdgeId for ById { fn to_string (& self) -> String { self . 0 . clone () } fn family_name (& self) -> String { stringify ! (By) . to_string () } } # [derive (Debug , Serialize , Deserialize , Clone)] pub enum ByConnection { SongIsBy (SongId , ArtistId) , AlbumIsBy (AlbumId , ArtistId) } impl EdgeConnection for ByConnection { } # [derive (Debug , Serialize , Deserialize , Clone)] pub struct By { id : ById , connection : ByConnection , weight : f64 , } impl By { pub fn new (id : Option < String > , connection : ByConnection , weight : f64 ,) -> Self { Self { id : ById (format ! (concat ! (stringify ! (By) , ":{}") , id . unwrap_or_else (|| xid :: new () . to_string ()))) , connection , weight } } pub fn id (& self) -> & ById { & self . id } } impl std :: str :: FromStr for By { type Err = serde_json :: Error ; fn from_str (s : & str) -> Result < Self , Self :: Err > { serde_json :: from_str :: < Self > (s) } } impl Edge for By { type Id = ById ; type Connection = ByConnection ; fn id (& self) -> & Self :: Id { & self . id } fn connection (& self) -> & Self :: Connection { & self . connection } fn family_name (& self) -> String { stringify ! (By) . to_string () } }
-# [derive (Debug , Serialize , Deserialize , Clone , PartialEq)] pub struct SongId (String) ; impl NodeId for SongId { fn new (id : Option < String >) -> Self { Self (format ! (concat ! (stringify ! (Song) , ":{}") , id . unwrap_or_else (|| xid :: new () . to_string ()))) } fn to_string (& self) -> String { self . 0 . clone () } fn family_name (& self) -> String { stringify ! (Song) . to_string () } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq)] pub enum SongInEdge { } impl NodeValidInEdgeId for SongInEdge { } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq)] pub enum SongOutEdge { ById (ById) , } impl NodeValidOutEdgeId for SongOutEdge { } # [derive (Debug , Serialize , Deserialize , Clone)] pub struct Song { id : SongId , in_edge_ids : Vec < SongInEdge > , out_edge_ids : Vec < SongOutEdge > , title : String , } impl Song { pub fn new (id : Option < String > , title : String ,) -> Self { Self { id : SongId (format ! (concat ! (stringify ! (Song) , ":{}") , id . unwrap_or (xid :: new () . to_string ()))) , in_edge_ids : Vec :: new () , out_edge_ids : Vec :: new () , title , } } pub fn new_id (id : String) -> SongId { SongId (format ! (concat ! (stringify ! (Song) , ":{}") , id)) } } impl std :: str :: FromStr for Song { type Err = serde_json :: Error ; fn from_str (s : & str) -> Result < Self , Self :: Err > { serde_json :: from_str :: < Self > (s) } } impl Node for Song { type Id = SongId ; type ValidInEdgeId = SongInEdge ; type ValidOutEdgeId = SongOutEdge ; fn id (& self) -> & SongId { & self . id } fn in_edge_ids (& self) -> Vec < Self :: ValidInEdgeId > { self . in_edge_ids . clone () } fn add_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) { self . in_edge_ids . push (edge_id) ; } fn remove_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) { self . in_edge_ids . retain (| x | x != & edge_id) ; } fn out_edge_ids (& self) -> Vec < Self :: ValidOutEdgeId > { self . out_edge_ids . clone () } fn add_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) { self . out_edge_ids . push (edge_id) ; } fn remove_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) { self . out_edge_ids . retain (| x | x != & edge_id) ; } fn family_name (& self) -> String { stringify ! (Song) . to_string () } }
-# [derive (Debug , Serialize , Deserialize , Clone , PartialEq)] pub struct ArtistId (String) ; impl NodeId for ArtistId { fn new (id : Option < String >) -> Self { Self (format ! (concat ! (stringify ! (Artist) , ":{}") , id . unwrap_or_else (|| xid :: new () . to_string ()))) } fn to_string (& self) -> String { self . 0 . clone () } fn family_name (& self) -> String { stringify ! (Artist) . to_string () } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq)] pub enum ArtistInEdge { ById (ById) , } impl NodeValidInEdgeId for ArtistInEdge { } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq)] pub enum ArtistOutEdge { } impl NodeValidOutEdgeId for ArtistOutEdge { } # [derive (Debug , Serialize , Deserialize , Clone)] pub struct Artist { id : ArtistId , in_edge_ids : Vec < ArtistInEdge > , out_edge_ids : Vec < ArtistOutEdge > , name : String , } impl Artist { pub fn new (id : Option < String > , name : String ,) -> Self { Self { id : ArtistId (format ! (concat ! (stringify ! (Artist) , ":{}") , id . unwrap_or (xid :: new () . to_string ()))) , in_edge_ids : Vec :: new () , out_edge_ids : Vec :: new () , name , } } pub fn new_id (id : String) -> ArtistId { ArtistId (format ! (concat ! (stringify ! (Artist) , ":{}") , id)) } } impl std :: str :: FromStr for Artist { type Err = serde_json :: Error ; fn from_str (s : & str) -> Result < Self , Self :: Err > { serde_json :: from_str :: < Self > (s) } } impl Node for Artist { type Id = ArtistId ; type ValidInEdgeId = ArtistInEdge ; type ValidOutEdgeId = ArtistOutEdge ; fn id (& self) -> & ArtistId { & self . id } fn in_edge_ids (& self) -> Vec < Self :: ValidInEdgeId > { self . in_edge_ids . clone () } fn add_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) { self . in_edge_ids . push (edge_id) ; } fn remove_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) { self . in_edge_ids . retain (| x | x != & edge_id) ; } fn out_edge_ids (& self) -> Vec < Self :: ValidOutEdgeId > { self . out_edge_ids . clone () } fn add_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) { self . out_edge_ids . push (edge_id) ; } fn remove_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) { self . out_edge_ids . retain (| x | x != & edge_id) ; } fn family_name (& self) -> String { stringify ! (Artist) . to_string () } }
-# [derive (Debug , Serialize , Deserialize , Clone , PartialEq)] pub struct AlbumId (String) ; impl NodeId for AlbumId { fn new (id : Option < String >) -> Self { Self (format ! (concat ! (stringify ! (Album) , ":{}") , id . unwrap_or_else (|| xid :: new () . to_string ()))) } fn to_string (& self) -> String { self . 0 . clone () } fn family_name (& self) -> String { stringify ! (Album) . to_string () } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq)] pub enum AlbumInEdge { } impl NodeValidInEdgeId for AlbumInEdge { } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq)] pub enum AlbumOutEdge { ById (ById) , } impl NodeValidOutEdgeId for AlbumOutEdge { } # [derive (Debug , Serialize , Deserialize , Clone)] pub struct Album { id : AlbumId , in_edge_ids : Vec < AlbumInEdge > , out_edge_ids : Vec < AlbumOutEdge > , title : String , } impl Album { pub fn new (id : Option < String > , title : String ,) -> Self { Self { id : AlbumId (format ! (concat ! (stringify ! (Album) , ":{}") , id . unwrap_or (xid :: new () . to_string ()))) , in_edge_ids : Vec :: new () , out_edge_ids : Vec :: new () , title , } } pub fn new_id (id : String) -> AlbumId { AlbumId (format ! (concat ! (stringify ! (Album) , ":{}") , id)) } } impl std :: str :: FromStr for Album { type Err = serde_json :: Error ; fn from_str (s : & str) -> Result < Self , Self :: Err > { serde_json :: from_str :: < Self > (s) } } impl Node for Album { type Id = AlbumId ; type ValidInEdgeId = AlbumInEdge ; type ValidOutEdgeId = AlbumOutEdge ; fn id (& self) -> & AlbumId { & self . id } fn in_edge_ids (& self) -> Vec < Self :: ValidInEdgeId > { self . in_edge_ids . clone () } fn add_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) { self . in_edge_ids . push (edge_id) ; } fn remove_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) { self . in_edge_ids . retain (| x | x != & edge_id) ; } fn out_edge_ids (& self) -> Vec < Self :: ValidOutEdgeId > { self . out_edge_ids . clone () } fn add_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) { self . out_edge_ids . push (edge_id) ; } fn remove_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) { self . out_edge_ids . retain (| x | x != & edge_id) ; } fn family_name (& self) -> String { stringify ! (Album) . to_string () } }
-pub fn families () -> Vec < & 'static str > { vec ! ["By"] }
+use serde :: { Serialize , Deserialize } ;
+pub trait NodeId : Serialize + for < 'de > Deserialize < 'de > + Clone + std :: fmt :: Debug + std :: fmt :: Display { fn new (id : Option < String >) -> Self ; fn family_name (& self) -> String ; } pub trait NodeValidInEdgeId : Serialize + for < 'de > Deserialize < 'de > + Clone + std :: fmt :: Debug { } pub trait NodeValidOutEdgeId : Serialize + for < 'de > Deserialize < 'de > + Clone + std :: fmt :: Debug { } pub trait Node : Serialize + for < 'de > Deserialize < 'de > + Clone + std :: fmt :: Debug { type Id : NodeId ; type ValidInEdgeId : NodeValidInEdgeId ; type ValidOutEdgeId : NodeValidOutEdgeId ; const FAMILY : & 'static str ; const FIELDS : & 'static [& 'static str] ; fn id (& self) -> & Self :: Id ; fn in_edge_ids (& self) -> Vec < Self :: ValidInEdgeId > ; fn out_edge_ids (& self) -> Vec < Self :: ValidOutEdgeId > ; fn add_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) ; fn remove_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) ; fn add_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) ; fn remove_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) ; fn family_name (& self) -> String ; fn sort_key (& self) -> String ; fn created_at (& self) -> Option < i64 > ; fn updated_at (& self) -> Option < i64 > ; fn touch (& mut self) ; fn family_name_static () -> & 'static str { Self :: FAMILY } }
+pub trait EdgeId : Serialize + for < 'de > Deserialize < 'de > + Clone + std :: fmt :: Debug + std :: fmt :: Display { fn family_name (& self) -> String ; } pub trait EdgeConnection : Serialize + for < 'de > Deserialize < 'de > + Clone + std :: fmt :: Debug { fn endpoints (& self) -> (String , String) ; fn validate (& self) -> Result < () , String > ; } pub trait Edge : Serialize + for < 'de > Deserialize < 'de > + Clone + std :: fmt :: Debug { type Id : EdgeId ; type Connection : EdgeConnection ; const FAMILY : & 'static str ; fn id (& self) -> & Self :: Id ; fn connection (& self) -> & Self :: Connection ; fn created_at (& self) -> Option < i64 > ; fn family_name (& self) -> String ; fn family_name_static () -> & 'static str { Self :: FAMILY } }
+# [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub struct ById (String) ; impl EdgeId for ById { fn family_name (& self) -> String { stringify ! (By) . to_string () } } impl std :: fmt :: Display for ById { fn fmt (& self , f : & mut std :: fmt :: Formatter < '_ >) -> std :: fmt :: Result { f . write_str (& self . 0) } } impl std :: str :: FromStr for ById { type Err = crate :: GraphError ; fn from_str (s : & str) -> Result < Self , Self :: Err > { crate :: check_id_family (s , stringify ! (By)) ? ; Ok (Self (s . to_string ())) } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub enum ByConnection { SongIsBy (SongId , ArtistId) , AlbumIsBy (AlbumId , ArtistId) } impl EdgeConnection for ByConnection { fn endpoints (& self) -> (String , String) { match self { Self :: SongIsBy (from , to) => (from . to_string () , to . to_string ()) , Self :: AlbumIsBy (from , to) => (from . to_string () , to . to_string ()) , } } fn validate (& self) -> Result < () , String > { let (from , to) = self . endpoints () ; let (from_prefix , to_prefix) = match self { Self :: SongIsBy (..) => ("Song:" , "Artist:") , Self :: AlbumIsBy (..) => ("Album:" , "Artist:") , } ; match (from . starts_with (from_prefix) , to . starts_with (to_prefix)) { (false , _) => Err (from) , (_ , false) => Err (to) , _ => Ok (()) , } } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq ,)] pub struct By { id : ById , connection : ByConnection , weight : f64 , # [serde (default)] created_at : i64 , } impl By { pub fn new (id : Option < String > , connection : ByConnection , weight : f64 ,) -> Self { Self { id : ById (format ! (concat ! (stringify ! (By) , ":{}") , id . unwrap_or_else (crate :: generate_id))) , connection , created_at : crate :: timestamp_millis () , weight } } pub fn id (& self) -> & ById { & self . id } # [doc = r" Decodes the msgpack form the graph stores. `FromStr` reads JSON."] pub fn from_msgpack (bytes : & [u8]) -> Result < Self , rmp_serde :: decode :: Error > { rmp_serde :: from_slice (bytes) } pub fn to_msgpack (& self) -> Result < Vec < u8 > , rmp_serde :: encode :: Error > { rmp_serde :: to_vec_named (self) } pub fn weight (& self) -> & f64 { & self . weight } } impl std :: str :: FromStr for By { type Err = serde_json :: Error ; fn from_str (s : & str) -> Result < Self , Self :: Err > { serde_json :: from_str :: < Self > (s) } } impl Edge for By { type Id = ById ; type Connection = ByConnection ; const FAMILY : & 'static str = stringify ! (By) ; fn id (& self) -> & Self :: Id { & self . id } fn connection (& self) -> & Self :: Connection { & self . connection } fn created_at (& self) -> Option < i64 > { Some (self . created_at) } fn family_name (& self) -> String { Self :: FAMILY . to_string () } }
+# [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub struct ContainsId (String) ; impl EdgeId for ContainsId { fn family_name (& self) -> String { stringify ! (Contains) . to_string () } } impl std :: fmt :: Display for ContainsId { fn fmt (& self , f : & mut std :: fmt :: Formatter < '_ >) -> std :: fmt :: Result { f . write_str (& self . 0) } } impl std :: str :: FromStr for ContainsId { type Err = crate :: GraphError ; fn from_str (s : & str) -> Result < Self , Self :: Err > { crate :: check_id_family (s , stringify ! (Contains)) ? ; Ok (Self (s . to_string ())) } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub enum ContainsConnection { PlaylistContainsSong (PlaylistId , SongId) } impl EdgeConnection for ContainsConnection { fn endpoints (& self) -> (String , String) { match self { Self :: PlaylistContainsSong (from , to) => (from . to_string () , to . to_string ()) , } } fn validate (& self) -> Result < () , String > { let (from , to) = self . endpoints () ; let (from_prefix , to_prefix) = match self { Self :: PlaylistContainsSong (..) => ("Playlist:" , "Song:") , } ; match (from . starts_with (from_prefix) , to . starts_with (to_prefix)) { (false , _) => Err (from) , (_ , false) => Err (to) , _ => Ok (()) , } } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub struct Contains { id : ContainsId , connection : ContainsConnection , } impl Contains { pub fn new (id : Option < String > , connection : ContainsConnection ,) -> Self { Self { id : ContainsId (format ! (concat ! (stringify ! (Contains) , ":{}") , id . unwrap_or_else (crate :: generate_id))) , connection , } } pub fn id (& self) -> & ContainsId { & self . id } # [doc = r" Decodes the msgpack form the graph stores. `FromStr` reads JSON."] pub fn from_msgpack (bytes : & [u8]) -> Result < Self , rmp_serde :: decode :: Error > { rmp_serde :: from_slice (bytes) } pub fn to_msgpack (& self) -> Result < Vec < u8 > , rmp_serde :: encode :: Error > { rmp_serde :: to_vec_named (self) } } impl std :: str :: FromStr for Contains { type Err = serde_json :: Error ; fn from_str (s : & str) -> Result < Self , Self :: Err > { serde_json :: from_str :: < Self > (s) } } impl Edge for Contains { type Id = ContainsId ; type Connection = ContainsConnection ; const FAMILY : & 'static str = stringify ! (Contains) ; fn id (& self) -> & Self :: Id { & self . id } fn connection (& self) -> & Self :: Connection { & self . connection } fn created_at (& self) -> Option < i64 > { None } fn family_name (& self) -> String { Self :: FAMILY . to_string () } }
+# [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub struct CollaboratedWithId (String) ; impl EdgeId for CollaboratedWithId { fn family_name (& self) -> String { stringify ! (CollaboratedWith) . to_string () } } impl std :: fmt :: Display for CollaboratedWithId { fn fmt (& self , f : & mut std :: fmt :: Formatter < '_ >) -> std :: fmt :: Result { f . write_str (& self . 0) } } impl std :: str :: FromStr for CollaboratedWithId { type Err = crate :: GraphError ; fn from_str (s : & str) -> Result < Self , Self :: Err > { crate :: check_id_family (s , stringify ! (CollaboratedWith)) ? ; Ok (Self (s . to_string ())) } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub enum CollaboratedWithConnection { ArtistCollaboratedWith (ArtistId , ArtistId) } impl EdgeConnection for CollaboratedWithConnection { fn endpoints (& self) -> (String , String) { match self { Self :: ArtistCollaboratedWith (from , to) => (from . to_string () , to . to_string ()) , } } fn validate (& self) -> Result < () , String > { let (from , to) = self . endpoints () ; let (from_prefix , to_prefix) = match self { Self :: ArtistCollaboratedWith (..) => ("Artist:" , "Artist:") , } ; match (from . starts_with (from_prefix) , to . starts_with (to_prefix)) { (false , _) => Err (from) , (_ , false) => Err (to) , _ => Ok (()) , } } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub struct CollaboratedWith { id : CollaboratedWithId , connection : CollaboratedWithConnection , } impl CollaboratedWith { pub fn new (id : Option < String > , connection : CollaboratedWithConnection ,) -> Self { Self { id : CollaboratedWithId (format ! (concat ! (stringify ! (CollaboratedWith) , ":{}") , id . unwrap_or_else (crate :: generate_id))) , connection , } } pub fn id (& self) -> & CollaboratedWithId { & self . id } # [doc = r" Decodes the msgpack form the graph stores. `FromStr` reads JSON."] pub fn from_msgpack (bytes : & [u8]) -> Result < Self , rmp_serde :: decode :: Error > { rmp_serde :: from_slice (bytes) } pub fn to_msgpack (& self) -> Result < Vec < u8 > , rmp_serde :: encode :: Error > { rmp_serde :: to_vec_named (self) } } impl std :: str :: FromStr for CollaboratedWith { type Err = serde_json :: Error ; fn from_str (s : & str) -> Result < Self , Self :: Err > { serde_json :: from_str :: < Self > (s) } } impl Edge for CollaboratedWith { type Id = CollaboratedWithId ; type Connection = CollaboratedWithConnection ; const FAMILY : & 'static str = stringify ! (CollaboratedWith) ; fn id (& self) -> & Self :: Id { & self . id } fn connection (& self) -> & Self :: Connection { & self . connection } fn created_at (& self) -> Option < i64 > { None } fn family_name (& self) -> String { Self :: FAMILY . to_string () } }
+# [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub struct SongId (String) ; impl NodeId for SongId { fn new (id : Option < String >) -> Self { Self (format ! (concat ! (stringify ! (Song) , ":{}") , id . unwrap_or_else (crate :: generate_id))) } fn family_name (& self) -> String { stringify ! (Song) . to_string () } } impl std :: fmt :: Display for SongId { fn fmt (& self , f : & mut std :: fmt :: Formatter < '_ >) -> std :: fmt :: Result { f . write_str (& self . 0) } } impl std :: str :: FromStr for SongId { type Err = crate :: GraphError ; fn from_str (s : & str) -> Result < Self , Self :: Err > { crate :: check_id_family (s , stringify ! (Song)) ? ; Ok (Self (s . to_string ())) } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub enum SongInEdge { ContainsId (ContainsId) , } impl NodeValidInEdgeId for SongInEdge { } impl From < ContainsId > for SongInEdge { fn from (edge_id : ContainsId) -> Self { Self :: ContainsId (edge_id) } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub enum SongOutEdge { ById (ById) , } impl NodeValidOutEdgeId for SongOutEdge { } impl From < ById > for SongOutEdge { fn from (edge_id : ById) -> Self { Self :: ById (edge_id) } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub struct Song { id : SongId , in_edge_ids : Vec < SongInEdge > , out_edge_ids : Vec < SongOutEdge > , title : String , tags : Vec < String > , } impl Song { pub fn new (id : Option < String > , title : String , tags : Vec < String > ,) -> Self { Self { id : SongId (format ! (concat ! (stringify ! (Song) , ":{}") , id . unwrap_or_else (crate :: generate_id))) , in_edge_ids : Vec :: new () , out_edge_ids : Vec :: new () , title , tags , } } pub fn new_id (id : String) -> SongId { SongId (format ! (concat ! (stringify ! (Song) , ":{}") , id)) } pub fn field_names () -> & 'static [& 'static str] { < Self as Node > :: FIELDS } pub fn builder () -> SongBuilder { SongBuilder :: default () } # [doc = r" Decodes the msgpack form the graph stores. `FromStr` reads JSON."] pub fn from_msgpack (bytes : & [u8]) -> Result < Self , rmp_serde :: decode :: Error > { rmp_serde :: from_slice (bytes) } pub fn to_msgpack (& self) -> Result < Vec < u8 > , rmp_serde :: encode :: Error > { rmp_serde :: to_vec_named (self) } pub fn title (& self) -> & String { & self . title } pub fn tags (& self) -> & Vec < String > { & self . tags } } # [derive (Debug , Clone , Default)] pub struct SongBuilder { id : Option < String > , title : Option < String > , tags : Option < Vec < String > > , } impl SongBuilder { pub fn with_id (mut self , id : String) -> Self { self . id = Some (id) ; self } pub fn with_title (mut self , title : String) -> Self { self . title = Some (title) ; self } pub fn with_tags (mut self , tags : Vec < String >) -> Self { self . tags = Some (tags) ; self } pub fn build (self) -> Result < Song , crate :: GraphError > { Ok (Song :: new (self . id , self . title . ok_or_else (|| crate :: GraphError :: MissingFieldError ("title" . to_string ())) ? , self . tags . ok_or_else (|| crate :: GraphError :: MissingFieldError ("tags" . to_string ())) ? ,)) } } impl std :: str :: FromStr for Song { type Err = serde_json :: Error ; fn from_str (s : & str) -> Result < Self , Self :: Err > { serde_json :: from_str :: < Self > (s) } } impl Node for Song { type Id = SongId ; type ValidInEdgeId = SongInEdge ; type ValidOutEdgeId = SongOutEdge ; const FAMILY : & 'static str = stringify ! (Song) ; const FIELDS : & 'static [& 'static str] = & [stringify ! (title) , stringify ! (tags)] ; fn id (& self) -> & SongId { & self . id } fn in_edge_ids (& self) -> Vec < Self :: ValidInEdgeId > { self . in_edge_ids . clone () } fn add_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) { self . in_edge_ids . push (edge_id) ; } fn remove_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) { self . in_edge_ids . retain (| x | x != & edge_id) ; } fn out_edge_ids (& self) -> Vec < Self :: ValidOutEdgeId > { self . out_edge_ids . clone () } fn add_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) { self . out_edge_ids . push (edge_id) ; } fn remove_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) { self . out_edge_ids . retain (| x | x != & edge_id) ; } fn family_name (& self) -> String { Self :: FAMILY . to_string () } fn sort_key (& self) -> String { self . id . 0 . clone () } fn created_at (& self) -> Option < i64 > { None } fn updated_at (& self) -> Option < i64 > { None } fn touch (& mut self) { } }
+# [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub struct ArtistId (String) ; impl NodeId for ArtistId { fn new (id : Option < String >) -> Self { Self (format ! (concat ! (stringify ! (Artist) , ":{}") , id . unwrap_or_else (crate :: generate_id))) } fn family_name (& self) -> String { stringify ! (Artist) . to_string () } } impl std :: fmt :: Display for ArtistId { fn fmt (& self , f : & mut std :: fmt :: Formatter < '_ >) -> std :: fmt :: Result { f . write_str (& self . 0) } } impl std :: str :: FromStr for ArtistId { type Err = crate :: GraphError ; fn from_str (s : & str) -> Result < Self , Self :: Err > { crate :: check_id_family (s , stringify ! (Artist)) ? ; Ok (Self (s . to_string ())) } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub enum ArtistInEdge { ById (ById) , CollaboratedWithId (CollaboratedWithId) , } impl NodeValidInEdgeId for ArtistInEdge { } impl From < ById > for ArtistInEdge { fn from (edge_id : ById) -> Self { Self :: ById (edge_id) } } impl From < CollaboratedWithId > for ArtistInEdge { fn from (edge_id : CollaboratedWithId) -> Self { Self :: CollaboratedWithId (edge_id) } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub enum ArtistOutEdge { CollaboratedWithId (CollaboratedWithId) , } impl NodeValidOutEdgeId for ArtistOutEdge { } impl From < CollaboratedWithId > for ArtistOutEdge { fn from (edge_id : CollaboratedWithId) -> Self { Self :: CollaboratedWithId (edge_id) } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub struct Artist { id : ArtistId , in_edge_ids : Vec < ArtistInEdge > , out_edge_ids : Vec < ArtistOutEdge > , # [serde (default = "default_artist_name")] name : String , # [serde (default = "default_artist_bio")] bio : Option < String > , } fn default_artist_name () -> String { String :: new () } fn default_artist_bio () -> Option < String > { None } impl Artist { pub fn new (id : Option < String > , name : String , bio : Option < String > ,) -> Self { Self { id : ArtistId (format ! (concat ! (stringify ! (Artist) , ":{}") , id . unwrap_or_else (crate :: generate_id))) , in_edge_ids : Vec :: new () , out_edge_ids : Vec :: new () , name , bio , } } pub fn new_id (id : String) -> ArtistId { ArtistId (format ! (concat ! (stringify ! (Artist) , ":{}") , id)) } pub fn field_names () -> & 'static [& 'static str] { < Self as Node > :: FIELDS } pub fn builder () -> ArtistBuilder { ArtistBuilder :: default () } # [doc = r" Decodes the msgpack form the graph stores. `FromStr` reads JSON."] pub fn from_msgpack (bytes : & [u8]) -> Result < Self , rmp_serde :: decode :: Error > { rmp_serde :: from_slice (bytes) } pub fn to_msgpack (& self) -> Result < Vec < u8 > , rmp_serde :: encode :: Error > { rmp_serde :: to_vec_named (self) } pub fn name (& self) -> & String { & self . name } pub fn bio (& self) -> & Option < String > { & self . bio } } # [derive (Debug , Clone , Default)] pub struct ArtistBuilder { id : Option < String > , name : Option < String > , bio : Option < String > , } impl ArtistBuilder { pub fn with_id (mut self , id : String) -> Self { self . id = Some (id) ; self } pub fn with_name (mut self , name : String) -> Self { self . name = Some (name) ; self } pub fn with_bio (mut self , bio : String) -> Self { self . bio = Some (bio) ; self } pub fn build (self) -> Result < Artist , crate :: GraphError > { Ok (Artist :: new (self . id , self . name . unwrap_or_else (default_artist_name) , self . bio . map (Some) . unwrap_or_else (default_artist_bio) ,)) } } impl Default for Artist { fn default () -> Self { Self { id : ArtistId :: new (None) , in_edge_ids : Vec :: new () , out_edge_ids : Vec :: new () , name : String :: new () , bio : None , } } } impl std :: str :: FromStr for Artist { type Err = serde_json :: Error ; fn from_str (s : & str) -> Result < Self , Self :: Err > { serde_json :: from_str :: < Self > (s) } } impl Node for Artist { type Id = ArtistId ; type ValidInEdgeId = ArtistInEdge ; type ValidOutEdgeId = ArtistOutEdge ; const FAMILY : & 'static str = stringify ! (Artist) ; const FIELDS : & 'static [& 'static str] = & [stringify ! (name) , stringify ! (bio)] ; fn id (& self) -> & ArtistId { & self . id } fn in_edge_ids (& self) -> Vec < Self :: ValidInEdgeId > { self . in_edge_ids . clone () } fn add_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) { self . in_edge_ids . push (edge_id) ; } fn remove_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) { self . in_edge_ids . retain (| x | x != & edge_id) ; } fn out_edge_ids (& self) -> Vec < Self :: ValidOutEdgeId > { self . out_edge_ids . clone () } fn add_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) { self . out_edge_ids . push (edge_id) ; } fn remove_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) { self . out_edge_ids . retain (| x | x != & edge_id) ; } fn family_name (& self) -> String { Self :: FAMILY . to_string () } fn sort_key (& self) -> String { self . name . to_string () } fn created_at (& self) -> Option < i64 > { None } fn updated_at (& self) -> Option < i64 > { None } fn touch (& mut self) { } }
+# [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub struct AlbumId (String) ; impl NodeId for AlbumId { fn new (id : Option < String >) -> Self { Self (format ! (concat ! (stringify ! (Album) , ":{}") , id . unwrap_or_else (crate :: generate_id))) } fn family_name (& self) -> String { stringify ! (Album) . to_string () } } impl std :: fmt :: Display for AlbumId { fn fmt (& self , f : & mut std :: fmt :: Formatter < '_ >) -> std :: fmt :: Result { f . write_str (& self . 0) } } impl std :: str :: FromStr for AlbumId { type Err = crate :: GraphError ; fn from_str (s : & str) -> Result < Self , Self :: Err > { crate :: check_id_family (s , stringify ! (Album)) ? ; Ok (Self (s . to_string ())) } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub enum AlbumInEdge { } impl NodeValidInEdgeId for AlbumInEdge { } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub enum AlbumOutEdge { ById (ById) , } impl NodeValidOutEdgeId for AlbumOutEdge { } impl From < ById > for AlbumOutEdge { fn from (edge_id : ById) -> Self { Self :: ById (edge_id) } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub struct Album { id : AlbumId , in_edge_ids : Vec < AlbumInEdge > , out_edge_ids : Vec < AlbumOutEdge > , title : String , artwork : Vec < u8 > , year : i32 , } impl Album { pub fn new (id : Option < String > , title : String , artwork : Vec < u8 > , year : i32 ,) -> Self { Self { id : AlbumId (format ! (concat ! (stringify ! (Album) , ":{}") , id . unwrap_or_else (crate :: generate_id))) , in_edge_ids : Vec :: new () , out_edge_ids : Vec :: new () , title , artwork , year , } } pub fn new_id (id : String) -> AlbumId { AlbumId (format ! (concat ! (stringify ! (Album) , ":{}") , id)) } pub fn field_names () -> & 'static [& 'static str] { < Self as Node > :: FIELDS } pub fn builder () -> AlbumBuilder { AlbumBuilder :: default () } # [doc = r" Decodes the msgpack form the graph stores. `FromStr` reads JSON."] pub fn from_msgpack (bytes : & [u8]) -> Result < Self , rmp_serde :: decode :: Error > { rmp_serde :: from_slice (bytes) } pub fn to_msgpack (& self) -> Result < Vec < u8 > , rmp_serde :: encode :: Error > { rmp_serde :: to_vec_named (self) } pub fn title (& self) -> & String { & self . title } pub fn artwork (& self) -> & [u8] { & self . artwork } pub fn year (& self) -> & i32 { & self . year } } # [derive (Debug , Clone , Default)] pub struct AlbumBuilder { id : Option < String > , title : Option < String > , artwork : Option < Vec < u8 > > , year : Option < i32 > , } impl AlbumBuilder { pub fn with_id (mut self , id : String) -> Self { self . id = Some (id) ; self } pub fn with_title (mut self , title : String) -> Self { self . title = Some (title) ; self } pub fn with_artwork (mut self , artwork : Vec < u8 >) -> Self { self . artwork = Some (artwork) ; self } pub fn with_year (mut self , year : i32) -> Self { self . year = Some (year) ; self } pub fn build (self) -> Result < Album , crate :: GraphError > { Ok (Album :: new (self . id , self . title . ok_or_else (|| crate :: GraphError :: MissingFieldError ("title" . to_string ())) ? , self . artwork . ok_or_else (|| crate :: GraphError :: MissingFieldError ("artwork" . to_string ())) ? , self . year . ok_or_else (|| crate :: GraphError :: MissingFieldError ("year" . to_string ())) ? ,)) } } impl std :: str :: FromStr for Album { type Err = serde_json :: Error ; fn from_str (s : & str) -> Result < Self , Self :: Err > { serde_json :: from_str :: < Self > (s) } } impl Node for Album { type Id = AlbumId ; type ValidInEdgeId = AlbumInEdge ; type ValidOutEdgeId = AlbumOutEdge ; const FAMILY : & 'static str = stringify ! (Album) ; const FIELDS : & 'static [& 'static str] = & [stringify ! (title) , stringify ! (artwork) , stringify ! (year)] ; fn id (& self) -> & AlbumId { & self . id } fn in_edge_ids (& self) -> Vec < Self :: ValidInEdgeId > { self . in_edge_ids . clone () } fn add_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) { self . in_edge_ids . push (edge_id) ; } fn remove_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) { self . in_edge_ids . retain (| x | x != & edge_id) ; } fn out_edge_ids (& self) -> Vec < Self :: ValidOutEdgeId > { self . out_edge_ids . clone () } fn add_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) { self . out_edge_ids . push (edge_id) ; } fn remove_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) { self . out_edge_ids . retain (| x | x != & edge_id) ; } fn family_name (& self) -> String { Self :: FAMILY . to_string () } fn sort_key (& self) -> String { self . id . 0 . clone () } fn created_at (& self) -> Option < i64 > { None } fn updated_at (& self) -> Option < i64 > { None } fn touch (& mut self) { } }
+# [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub struct PlaylistId (String) ; impl NodeId for PlaylistId { fn new (id : Option < String >) -> Self { Self (format ! (concat ! (stringify ! (Playlist) , ":{}") , id . unwrap_or_else (crate :: generate_id))) } fn family_name (& self) -> String { stringify ! (Playlist) . to_string () } } impl std :: fmt :: Display for PlaylistId { fn fmt (& self , f : & mut std :: fmt :: Formatter < '_ >) -> std :: fmt :: Result { f . write_str (& self . 0) } } impl std :: str :: FromStr for PlaylistId { type Err = crate :: GraphError ; fn from_str (s : & str) -> Result < Self , Self :: Err > { crate :: check_id_family (s , stringify ! (Playlist)) ? ; Ok (Self (s . to_string ())) } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub enum PlaylistInEdge { } impl NodeValidInEdgeId for PlaylistInEdge { } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub enum PlaylistOutEdge { ContainsId (ContainsId) , } impl NodeValidOutEdgeId for PlaylistOutEdge { } impl From < ContainsId > for PlaylistOutEdge { fn from (edge_id : ContainsId) -> Self { Self :: ContainsId (edge_id) } } # [derive (Debug , Serialize , Deserialize , Clone , PartialEq , Eq , Hash)] pub struct Playlist { id : PlaylistId , in_edge_ids : Vec < PlaylistInEdge > , out_edge_ids : Vec < PlaylistOutEdge > , # [serde (default)] created_at : i64 , # [serde (default)] updated_at : i64 , } impl Playlist { pub fn new (id : Option < String > ,) -> Self { Self { id : PlaylistId (format ! (concat ! (stringify ! (Playlist) , ":{}") , id . unwrap_or_else (crate :: generate_id))) , in_edge_ids : Vec :: new () , out_edge_ids : Vec :: new () , created_at : crate :: timestamp_millis () , updated_at : crate :: timestamp_millis () , } } pub fn new_id (id : String) -> PlaylistId { PlaylistId (format ! (concat ! (stringify ! (Playlist) , ":{}") , id)) } pub fn field_names () -> & 'static [& 'static str] { < Self as Node > :: FIELDS } pub fn builder () -> PlaylistBuilder { PlaylistBuilder :: default () } # [doc = r" Decodes the msgpack form the graph stores. `FromStr` reads JSON."] pub fn from_msgpack (bytes : & [u8]) -> Result < Self , rmp_serde :: decode :: Error > { rmp_serde :: from_slice (bytes) } pub fn to_msgpack (& self) -> Result < Vec < u8 > , rmp_serde :: encode :: Error > { rmp_serde :: to_vec_named (self) } } # [derive (Debug , Clone , Default)] pub struct PlaylistBuilder { id : Option < String > , } impl PlaylistBuilder { pub fn with_id (mut self , id : String) -> Self { self . id = Some (id) ; self } pub fn build (self) -> Result < Playlist , crate :: GraphError > { Ok (Playlist :: new (self . id ,)) } } impl Default for Playlist { fn default () -> Self { Self { id : PlaylistId :: new (None) , in_edge_ids : Vec :: new () , out_edge_ids : Vec :: new () , created_at : crate :: timestamp_millis () , updated_at : crate :: timestamp_millis () , } } } impl std :: str :: FromStr for Playlist { type Err = serde_json :: Error ; fn from_str (s : & str) -> Result < Self , Self :: Err > { serde_json :: from_str :: < Self > (s) } } impl Node for Playlist { type Id = PlaylistId ; type ValidInEdgeId = PlaylistInEdge ; type ValidOutEdgeId = PlaylistOutEdge ; const FAMILY : & 'static str = stringify ! (Playlist) ; const FIELDS : & 'static [& 'static str] = & [] ; fn id (& self) -> & PlaylistId { & self . id } fn in_edge_ids (& self) -> Vec < Self :: ValidInEdgeId > { self . in_edge_ids . clone () } fn add_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) { self . in_edge_ids . push (edge_id) ; } fn remove_in_edge_id (& mut self , edge_id : Self :: ValidInEdgeId) { self . in_edge_ids . retain (| x | x != & edge_id) ; } fn out_edge_ids (& self) -> Vec < Self :: ValidOutEdgeId > { self . out_edge_ids . clone () } fn add_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) { self . out_edge_ids . push (edge_id) ; } fn remove_out_edge_id (& mut self , edge_id : Self :: ValidOutEdgeId) { self . out_edge_ids . retain (| x | x != & edge_id) ; } fn family_name (& self) -> String { Self :: FAMILY . to_string () } fn sort_key (& self) -> String { self . id . 0 . clone () } fn created_at (& self) -> Option < i64 > { Some (self . created_at) } fn updated_at (& self) -> Option < i64 > { Some (self . updated_at) } fn touch (& mut self) { self . updated_at = crate :: timestamp_millis () ; } }
+# [derive (Debug , Clone)] pub enum AnyNode { Song (Song) , Artist (Artist) , Album (Album) , Playlist (Playlist) } impl AnyNode { pub fn decode < C : crate :: Codec > (codec : & C , family : & str , bytes : & [u8]) -> Option < Result < Self , crate :: GraphError >> { match family { "Song" => Some (codec . decode (bytes) . map (Self :: Song)) , "Artist" => Some (codec . decode (bytes) . map (Self :: Artist)) , "Album" => Some (codec . decode (bytes) . map (Self :: Album)) , "Playlist" => Some (codec . decode (bytes) . map (Self :: Playlist)) , _ => None , } } pub fn id (& self) -> String { match self { Self :: Song (node) => node . id () . to_string () , Self :: Artist (node) => node . id () . to_string () , Self :: Album (node) => node . id () . to_string () , Self :: Playlist (node) => node . id () . to_string () , } } pub fn family_name (& self) -> & 'static str { match self { Self :: Song (_) => < Song as Node > :: FAMILY , Self :: Artist (_) => < Artist as Node > :: FAMILY , Self :: Album (_) => < Album as Node > :: FAMILY , Self :: Playlist (_) => < Playlist as Node > :: FAMILY , } } pub fn in_degree (& self) -> usize { match self { Self :: Song (node) => node . in_edge_ids . len () , Self :: Artist (node) => node . in_edge_ids . len () , Self :: Album (node) => node . in_edge_ids . len () , Self :: Playlist (node) => node . in_edge_ids . len () , } } pub fn out_degree (& self) -> usize { match self { Self :: Song (node) => node . out_edge_ids . len () , Self :: Artist (node) => node . out_edge_ids . len () , Self :: Album (node) => node . out_edge_ids . len () , Self :: Playlist (node) => node . out_edge_ids . len () , } } } impl From < Song > for AnyNode { fn from (node : Song) -> Self { Self :: Song (node) } } impl From < Artist > for AnyNode { fn from (node : Artist) -> Self { Self :: Artist (node) } } impl From < Album > for AnyNode { fn from (node : Album) -> Self { Self :: Album (node) } } impl From < Playlist > for AnyNode { fn from (node : Playlist) -> Self { Self :: Playlist (node) } }
+# [derive (Debug , Clone)] pub enum AnyEdge { By (By) , Contains (Contains) , CollaboratedWith (CollaboratedWith) } impl AnyEdge { pub fn decode < C : crate :: Codec > (codec : & C , family : & str , bytes : & [u8]) -> Option < Result < Self , crate :: GraphError >> { match family { "By" => Some (codec . decode (bytes) . map (Self :: By)) , "Contains" => Some (codec . decode (bytes) . map (Self :: Contains)) , "CollaboratedWith" => Some (codec . decode (bytes) . map (Self :: CollaboratedWith)) , _ => None , } } pub fn id (& self) -> String { match self { Self :: By (edge) => edge . id () . to_string () , Self :: Contains (edge) => edge . id () . to_string () , Self :: CollaboratedWith (edge) => edge . id () . to_string () , } } pub fn family_name (& self) -> & 'static str { match self { Self :: By (_) => < By as Edge > :: FAMILY , Self :: Contains (_) => < Contains as Edge > :: FAMILY , Self :: CollaboratedWith (_) => < CollaboratedWith as Edge > :: FAMILY , } } pub fn endpoints (& self) -> (String , String) { match self { Self :: By (edge) => edge . connection () . endpoints () , Self :: Contains (edge) => edge . connection () . endpoints () , Self :: CollaboratedWith (edge) => edge . connection () . endpoints () , } } } impl From < By > for AnyEdge { fn from (edge : By) -> Self { Self :: By (edge) } } impl From < Contains > for AnyEdge { fn from (edge : Contains) -> Self { Self :: Contains (edge) } } impl From < CollaboratedWith > for AnyEdge { fn from (edge : CollaboratedWith) -> Self { Self :: CollaboratedWith (edge) } }
+pub fn families () -> Vec < & 'static str > { vec ! ["By" , "Contains" , "CollaboratedWith" , "Song" , "Song_by_title" , "Artist" , "Album" , "Album_by_year" , "Playlist"] } pub fn node_families () -> Vec < & 'static str > { vec ! ["Song" , "Artist" , "Album" , "Playlist"] } pub fn edge_families () -> Vec < & 'static str > { vec ! ["By" , "Contains" , "CollaboratedWith"] } pub fn undirected_edge_families () -> Vec < & 'static str > { vec ! ["CollaboratedWith"] } pub fn index_families () -> Vec < & 'static str > { vec ! ["Song_by_title" , "Album_by_year"] } pub fn indexed_fields (family : & str) -> & 'static [& 'static str] { match family { "Song" => & ["title"] , "Album" => & ["year"] , _ => & [] , } } pub fn range_indexed_fields (family : & str) -> & 'static [& 'static str] { match family { "Album" => & ["year"] , _ => & [] , } } pub fn family_prefix_length (family : & str) -> Option < usize > { match family { "Song" => Some (9) , _ => None , } }
+pub fn schema_json () -> & 'static str { "{\"nodes\":[{\"name\":\"Song\",\"fields\":[{\"name\":\"title\",\"type\":\"String\",\"default\":null,\"indexed\":true},{\"name\":\"tags\",\"type\":\"Vec<String>\",\"default\":null,\"indexed\":false}],\"sort_by\":null,\"prefix_extractor\":4,\"timestamps\":false},{\"name\":\"Artist\",\"fields\":[{\"name\":\"name\",\"type\":\"String\",\"default\":\"String::new()\",\"indexed\":false},{\"name\":\"bio\",\"type\":\"Option<String>\",\"default\":\"None\",\"indexed\":false}],\"sort_by\":\"name\",\"prefix_extractor\":null,\"timestamps\":false},{\"name\":\"Album\",\"fields\":[{\"name\":\"title\",\"type\":\"String\",\"default\":null,\"indexed\":false},{\"name\":\"artwork\",\"type\":\"Vec<u8>\",\"default\":null,\"indexed\":false},{\"name\":\"year\",\"type\":\"i32\",\"default\":null,\"indexed\":true}],\"sort_by\":null,\"prefix_extractor\":null,\"timestamps\":false},{\"name\":\"Playlist\",\"fields\":[],\"sort_by\":null,\"prefix_extractor\":null,\"timestamps\":true}],\"edges\":[{\"name\":\"By\",\"connections\":[{\"from\":\"Song\",\"to\":\"Artist\",\"name\":\"SongIsBy\"},{\"from\":\"Album\",\"to\":\"Artist\",\"name\":\"AlbumIsBy\"}],\"fields\":[{\"name\":\"weight\",\"type\":\"f64\",\"default\":null,\"indexed\":false}],\"timestamps\":true,\"undirected\":false},{\"name\":\"Contains\",\"connections\":[{\"from\":\"Playlist\",\"to\":\"Song\",\"name\":\"PlaylistContainsSong\"}],\"fields\":[],\"timestamps\":false,\"undirected\":false},{\"name\":\"CollaboratedWith\",\"connections\":[{\"from\":\"Artist\",\"to\":\"Artist\",\"name\":\"ArtistCollaboratedWith\"}],\"fields\":[],\"timestamps\":false,\"undirected\":true}]}" }
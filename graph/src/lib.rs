@@ -1,395 +1,4218 @@
+mod codec;
 pub mod generated;
 
 use rocksdb::{
-    ColumnFamilyDescriptor, Error as RocksError, MultiThreaded, Options, Transaction,
-    TransactionDB, TransactionDBOptions, DB,
+    backup::{BackupEngine, BackupEngineOptions, RestoreOptions},
+    BlockBasedOptions, BoundColumnFamily, Cache, ColumnFamilyDescriptor, DBCompressionType, Env,
+    Error as RocksError, MultiThreaded, Options, SliceTransform, SnapshotWithThreadMode,
+    Transaction, TransactionDB, TransactionDBOptions, DB,
 };
 
+use lru::LruCache;
 use rmp_serde::{decode::Error as DecodeError, encode::Error as EncodeError};
-use std::{string::FromUtf8Error, sync::Arc};
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde_json::Value;
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    fmt,
+    io::{BufRead, Read, Write},
+    num::NonZeroUsize,
+    path::Path,
+    string::FromUtf8Error,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(feature = "metrics")]
+use metrics::LatencyHistogram;
+#[cfg(feature = "metrics")]
+use std::time::Instant;
 
+pub use codec::{Codec, JsonCodec, MsgpackCodec, StorageFormat};
 pub use generated::*;
+#[cfg(feature = "metrics")]
+pub use metrics::{LatencyReport, OperationLatency};
+pub use rocksdb::DBCompactionStyle;
 pub use serde::{Deserialize, Serialize};
 pub use xid;
 
+const WRITE_BATCH_SIZE: usize = 1000;
+const DEFAULT_BLOCK_CACHE_SIZE: usize = 8 * 1024 * 1024;
+const DEFAULT_WRITE_BUFFER_SIZE: usize = 64 * 1024 * 1024;
+const RESERVED_FIELDS: [&str; 3] = ["id", "in_edge_ids", "out_edge_ids"];
+const ADJACENCY_FAMILY: &str = "__adjacency";
+const META_FAMILY: &str = "__meta__";
+const FAMILY_OPTIONS_PREFIX: &str = "family_options:";
+const STORAGE_FORMAT_KEY: &str = "storage_format";
+
+type IdGenerator = Box<dyn Fn() -> String + Send + Sync>;
+
+static ID_GENERATOR: RwLock<Option<IdGenerator>> = RwLock::new(None);
+
+pub fn generate_id() -> String {
+    match ID_GENERATOR.read().unwrap().as_ref() {
+        Some(generator) => generator(),
+        None => xid::new().to_string(),
+    }
+}
+
+pub fn set_id_generator<F>(generator: F)
+where
+    F: Fn() -> String + Send + Sync + 'static,
+{
+    *ID_GENERATOR.write().unwrap() = Some(Box::new(generator));
+}
+
+pub fn reset_id_generator() {
+    *ID_GENERATOR.write().unwrap() = None;
+}
+
+pub fn use_deterministic_ids(seed: u32) {
+    let counter = AtomicU64::new(0);
+    set_id_generator(move || {
+        let mut raw = [0u8; 12];
+        raw[..4].copy_from_slice(&seed.to_be_bytes());
+        raw[4..].copy_from_slice(&counter.fetch_add(1, Ordering::SeqCst).to_be_bytes());
+        xid::Id(raw).to_string()
+    });
+}
+
+/// Best-effort rendering of a stored value from `Graph::scan_raw`: the record
+/// decoded as JSON or msgpack when possible, otherwise a hex dump.
+pub fn preview_raw(bytes: &[u8]) -> String {
+    // JSON first: msgpack would accept the leading byte of a JSON record.
+    let decoded = JsonCodec
+        .decode::<Value>(bytes)
+        .or_else(|_| MsgpackCodec.decode::<Value>(bytes));
+    match decoded {
+        Ok(value) => value.to_string(),
+        Err(_) => bytes
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+pub(crate) fn timestamp_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Encoded nodes by id. `generation` goes up on every invalidation, so a
+/// read that overlapped a write can tell its value may be stale and leave it
+/// out of the cache.
+struct NodeCache {
+    entries: LruCache<String, Vec<u8>>,
+    generation: u64,
+}
+
+#[derive(Clone)]
 pub struct Graph {
     db: Arc<TransactionDB<MultiThreaded>>,
     path: String,
+    strict_families: bool,
+    case_insensitive_families: bool,
+    family_options: FamilyOptions,
+    config: GraphConfig,
+    block_cache: Cache,
+    node_cache: Option<Arc<Mutex<NodeCache>>>,
+    codec: StorageFormat,
+    #[cfg(feature = "metrics")]
+    latencies: Arc<Mutex<HashMap<&'static str, LatencyHistogram>>>,
+}
+
+#[cfg(feature = "metrics")]
+struct LatencyTimer<'a> {
+    graph: &'a Graph,
+    operation: &'static str,
+    started: Instant,
+}
+
+#[cfg(not(feature = "metrics"))]
+struct LatencyTimer;
+
+pub struct GraphTxn<'a> {
+    graph: &'a Graph,
+    txn: Transaction<'a, TransactionDB<MultiThreaded>>,
+    written_ids: RefCell<Vec<String>>,
+}
+
+type DbSnapshot<'a> = SnapshotWithThreadMode<'a, TransactionDB<MultiThreaded>>;
+
+pub struct GraphSnapshot<'a> {
+    graph: &'a Graph,
+    snapshot: DbSnapshot<'a>,
+}
+
+pub struct GraphBuilder {
+    path: String,
+    strict_families: bool,
+    case_insensitive_families: bool,
+    wal_dir: Option<String>,
+    max_total_wal_size: Option<u64>,
+    block_cache_size: usize,
+    node_cache_capacity: usize,
+    family_options: FamilyOptions,
+    config: GraphConfig,
+    codec: StorageFormat,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Compression {
+    None,
+    Snappy,
+    Lz4,
+    Zstd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphConfig {
+    pub parallelism: i32,
+    pub write_buffer_size: usize,
+    pub max_open_files: i32,
+    pub compression: Compression,
+}
+
+/// Block cache usage only. Memtables and table readers aren't included:
+/// `MemoryUsageBuilder` takes a plain `DB`, and `TransactionDB` doesn't
+/// expose `rocksdb.cur-size-all-mem-tables` or
+/// `rocksdb.estimate-table-readers-mem`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub block_cache_usage: usize,
+    pub block_cache_pinned_usage: usize,
+}
+
+/// Counts are exact and come from a scan; `estimated_size_bytes` is the size
+/// of the SST files on disk and leaves out unflushed memtables.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphStats {
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub family_counts: BTreeMap<String, usize>,
+    pub family_count: usize,
+    pub estimated_size_bytes: u64,
+}
+
+impl std::fmt::Display for GraphStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Nodes: {}", self.node_count)?;
+        writeln!(f, "Edges: {}", self.edge_count)?;
+        writeln!(f, "Families: {}", self.family_count)?;
+        writeln!(f, "Estimated size: {} bytes", self.estimated_size_bytes)?;
+        for (family_name, count) in &self.family_counts {
+            writeln!(f, "  {}: {}", family_name, count)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FamilyOptions {
+    #[serde(with = "compaction_style")]
+    pub compaction_style: Option<DBCompactionStyle>,
+    pub level_zero_file_num_compaction_trigger: Option<i32>,
+    pub compression: Option<Compression>,
+    pub prefix_length: Option<usize>,
+}
+
+/// The storage operation that failed, carried by `GraphError::Rocks`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    OpenDb,
+    DestroyDb,
+    CreateFamily,
+    FindFamilies,
+    CreateNode,
+    ReadNode,
+    UpdateNode,
+    DeleteNode,
+    CreateEdge,
+    Delete,
+    Commit,
+    Rollback,
+}
+
+impl std::fmt::Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operation = match self {
+            Operation::OpenDb => "opening database",
+            Operation::DestroyDb => "destroying database",
+            Operation::CreateFamily => "creating family",
+            Operation::FindFamilies => "finding families",
+            Operation::CreateNode => "creating node",
+            Operation::ReadNode => "reading node",
+            Operation::UpdateNode => "updating node",
+            Operation::DeleteNode => "deleting node",
+            Operation::CreateEdge => "creating edge",
+            Operation::Delete => "deleting",
+            Operation::Commit => "committing transaction",
+            Operation::Rollback => "rolling back transaction",
+        };
+        f.write_str(operation)
+    }
+}
+
+/// Errors returned by graph operations.
+///
+/// # Migrating from the per-operation variants
+///
+/// RocksDB failures used to come back as one variant per operation. They
+/// are now all `GraphError::Rocks`, with the operation in `op`:
+///
+/// | Old variant          | Now                                          |
+/// |----------------------|----------------------------------------------|
+/// | `OpenDbError(e)`     | `Rocks { op: Operation::OpenDb, source: e }` |
+/// | `DestroyDbError`     | `Rocks { op: Operation::DestroyDb, .. }`     |
+/// | `CreateFamilyError`  | `Rocks { op: Operation::CreateFamily, .. }`  |
+/// | `FindFamiliesError`  | `Rocks { op: Operation::FindFamilies, .. }`  |
+/// | `CreateNodeError`    | `Rocks { op: Operation::CreateNode, .. }`    |
+/// | `ReadNodeError`      | `Rocks { op: Operation::ReadNode, .. }`      |
+/// | `UpdateNodeError`    | `Rocks { op: Operation::UpdateNode, .. }`    |
+/// | `DeleteNodeError`    | `Rocks { op: Operation::DeleteNode, .. }`    |
+/// | `CreateEdgeError`    | `Rocks { op: Operation::CreateEdge, .. }`    |
+/// | `DeleteError`        | `Rocks { op: Operation::Delete, .. }`        |
+/// | `RollbackError`      | `Rocks { op: Operation::Rollback, .. }`      |
+///
+/// Commit failures, which used to surface under whichever operation the
+/// transaction was doing, are `Operation::Commit`. Every other variant kept
+/// its name.
+#[derive(Debug, thiserror::Error)]
 pub enum GraphError {
-    EncodeError(EncodeError),
-    DecodeError(DecodeError),
-    OpenDbError(RocksError),
-    DestroyDbError(RocksError),
-    CreateNodeError(RocksError),
-    ReadNodeError(RocksError),
-    DeleteNodeError(RocksError),
-    UpdateNodeError(RocksError),
-    CreateEdgeError(RocksError),
-    DeleteError(RocksError),
-    FindFamiliesError(RocksError),
+    #[error("Error {op}: {source}")]
+    Rocks {
+        op: Operation,
+        #[source]
+        source: RocksError,
+    },
+    #[error("Backup error: {0}")]
+    BackupError(#[source] RocksError),
+    #[error("Error dropping family {failed} after dropping {dropped:?}: {source}")]
+    DestroyIncomplete {
+        dropped: Vec<String>,
+        failed: String,
+        #[source]
+        source: RocksError,
+    },
+    #[error("Encoding error: {0}")]
+    EncodeError(#[from] EncodeError),
+    #[error("Decoding error: {0}")]
+    DecodeError(#[from] DecodeError),
+    #[error("Tried to destroy database while it was still open")]
     DbNotClosed,
+    #[error("Find key error")]
     FindKeyError,
+    #[error("Neighbour index error")]
     NeighbourIndexError,
-    ParseUtf8Error(FromUtf8Error),
+    #[error("Parse UTF8 error: {0}")]
+    ParseUtf8Error(#[from] FromUtf8Error),
+    #[error("Error accessing node family")]
     NodeFamilyError,
-    CreateFamilyError(RocksError),
+    #[error("Error finding node family")]
     FindFamilyError,
+    #[error("Error parsing node id")]
     ParseNodeIdError,
+    #[error("Error accessing edge family")]
     EdgeFamilyError,
+    #[error("Unknown field: {0}")]
+    UnknownFieldError(String),
+    #[error("Missing field: {0}")]
+    MissingFieldError(String),
+    #[error("Field has no numeric range index: {0}")]
+    UnindexableField(String),
+    #[error("Connection endpoint has the wrong family: {0}")]
+    InvalidConnection(String),
+    #[error("Missing family: {0}")]
+    MissingFamilyError(String),
+    #[error("Family mismatch: expected {expected}, got {found}")]
+    FamilyMismatch { expected: String, found: String },
+    #[error("Invalid node: {0}")]
+    InvalidNodeError(String),
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
 }
 
-impl From<EncodeError> for GraphError {
-    fn from(error: EncodeError) -> Self {
-        GraphError::EncodeError(error)
-    }
+fn rocks(op: Operation) -> impl Fn(RocksError) -> GraphError {
+    move |source| GraphError::Rocks { op, source }
 }
 
-impl From<DecodeError> for GraphError {
-    fn from(error: DecodeError) -> Self {
-        GraphError::DecodeError(error)
+impl GraphBuilder {
+    pub fn new(path: &str) -> GraphBuilder {
+        GraphBuilder {
+            path: path.to_string(),
+            strict_families: false,
+            case_insensitive_families: false,
+            wal_dir: None,
+            max_total_wal_size: None,
+            block_cache_size: DEFAULT_BLOCK_CACHE_SIZE,
+            node_cache_capacity: 0,
+            family_options: FamilyOptions::default(),
+            config: GraphConfig::default(),
+            codec: StorageFormat::default(),
+        }
     }
-}
 
-impl From<FromUtf8Error> for GraphError {
-    fn from(error: FromUtf8Error) -> Self {
-        GraphError::ParseUtf8Error(error)
+    pub fn config(mut self, config: GraphConfig) -> GraphBuilder {
+        self.config = config;
+        self
     }
-}
 
-impl std::fmt::Display for GraphError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            GraphError::EncodeError(error) => write!(f, "Encoding error: {}", error),
-            GraphError::DecodeError(error) => write!(f, "Decoding error: {}", error),
-            GraphError::CreateNodeError(error) => write!(f, "Error creating node: {}", error),
-            GraphError::ReadNodeError(error) => write!(f, "Error reading node: {}", error),
-            GraphError::UpdateNodeError(error) => write!(f, "Error updating node: {}", error),
-            GraphError::DeleteNodeError(error) => write!(f, "Error deleting node: {}", error),
-            GraphError::CreateEdgeError(error) => write!(f, "Error creating edge: {}", error),
-            GraphError::DeleteError(error) => write!(f, "Error deleting: {}", error),
-            GraphError::FindKeyError => write!(f, "Find key error"),
-            GraphError::OpenDbError(error) => write!(f, "Error opening database: {}", error),
-            GraphError::DestroyDbError(error) => write!(f, "Error destroying database: {}", error),
-            GraphError::NeighbourIndexError => write!(f, "Neighbour index error"),
-            GraphError::ParseUtf8Error(error) => write!(f, "Parse UTF8 error: {}", error),
-            GraphError::NodeFamilyError => write!(f, "Error accessing node family"),
-            GraphError::CreateFamilyError(error) => write!(f, "Error creating family: {}", error),
-            GraphError::FindFamilyError => write!(f, "Error finding node family"),
-            GraphError::ParseNodeIdError => write!(f, "Error parsing node id"),
-            GraphError::EdgeFamilyError => write!(f, "Error accessing edge family"),
-            GraphError::FindFamiliesError(error) => write!(f, "Error finding families: {}", error),
-            GraphError::DbNotClosed => {
-                write!(f, "Tried to destroy database while it was still open")
-            }
-        }
+    pub fn strict_families(mut self, strict_families: bool) -> GraphBuilder {
+        self.strict_families = strict_families;
+        self
     }
-}
 
-impl Graph {
-    pub fn new(path: &str) -> Result<Graph, GraphError> {
+    pub fn case_insensitive_families(mut self, case_insensitive_families: bool) -> GraphBuilder {
+        self.case_insensitive_families = case_insensitive_families;
+        self
+    }
+
+    pub fn wal_dir(mut self, wal_dir: &str) -> GraphBuilder {
+        self.wal_dir = Some(wal_dir.to_string());
+        self
+    }
+
+    pub fn max_total_wal_size(mut self, bytes: u64) -> GraphBuilder {
+        self.max_total_wal_size = Some(bytes);
+        self
+    }
+
+    pub fn block_cache_size(mut self, bytes: usize) -> GraphBuilder {
+        self.block_cache_size = bytes;
+        self
+    }
+
+    pub fn node_cache(mut self, capacity: usize) -> GraphBuilder {
+        self.node_cache_capacity = capacity;
+        self
+    }
+
+    pub fn codec(mut self, codec: impl Into<StorageFormat>) -> GraphBuilder {
+        self.codec = codec.into();
+        self
+    }
+
+    pub fn compaction_style(mut self, style: DBCompactionStyle) -> GraphBuilder {
+        self.family_options.compaction_style = Some(style);
+        self
+    }
+
+    pub fn level_zero_file_num_compaction_trigger(mut self, trigger: i32) -> GraphBuilder {
+        self.family_options.level_zero_file_num_compaction_trigger = Some(trigger);
+        self
+    }
+
+    pub fn open(self) -> Result<Graph, GraphError> {
+        let path = self.path.as_str();
         let mut options = Options::default();
         options.create_if_missing(true);
+        if let Some(wal_dir) = &self.wal_dir {
+            options.set_wal_dir(wal_dir);
+        }
+        if let Some(max_total_wal_size) = self.max_total_wal_size {
+            options.set_max_total_wal_size(max_total_wal_size);
+        }
+        if self.config.parallelism > 1 {
+            options.increase_parallelism(self.config.parallelism);
+        }
+        options.set_max_open_files(self.config.max_open_files);
+        options.set_write_buffer_size(self.config.write_buffer_size);
+        options.set_compression_type(self.config.compression.into());
 
         let txn_db_options = TransactionDBOptions::default();
+        let block_cache =
+            Cache::new_lru_cache(self.block_cache_size).map_err(rocks(Operation::OpenDb))?;
 
-        let cfs = match DB::list_cf(&options, path) {
-            Ok(cfs) => cfs,
-            Err(_) => Vec::new(), // If there are no existing column families
+        let cfs = match Path::new(path).join("CURRENT").exists() {
+            true => DB::list_cf(&options, path).map_err(rocks(Operation::OpenDb))?,
+            false => Vec::new(), // A fresh database has no column families yet
+        };
+
+        let stored_family_options = match cfs.iter().any(|cf| cf == META_FAMILY) {
+            true => read_family_options(&options, path)?,
+            false => HashMap::new(),
         };
 
         let mut cf_descriptors = Vec::new();
         for cf in cfs {
-            cf_descriptors.push(ColumnFamilyDescriptor::new(cf, Options::default()));
+            let family_options = stored_family_options
+                .get(&cf)
+                .copied()
+                .unwrap_or_else(|| self.family_options.recommended_for(&cf));
+            cf_descriptors.push(ColumnFamilyDescriptor::new(
+                cf,
+                family_options.to_options(&block_cache, &self.config),
+            ));
         }
 
         let db: TransactionDB<MultiThreaded> = match cf_descriptors.is_empty() {
             true => TransactionDB::open(&options, &txn_db_options, path)
-                .map_err(GraphError::OpenDbError)?,
+                .map_err(rocks(Operation::OpenDb))?,
             false => {
                 TransactionDB::open_cf_descriptors(&options, &txn_db_options, path, cf_descriptors)
-                    .map_err(GraphError::OpenDbError)?
+                    .map_err(rocks(Operation::OpenDb))?
             }
         };
 
-        let path = path.to_string();
-
         let graph = Graph {
             db: Arc::new(db),
-            path,
+            path: self.path,
+            strict_families: self.strict_families,
+            case_insensitive_families: self.case_insensitive_families,
+            family_options: self.family_options,
+            config: self.config,
+            block_cache,
+            node_cache: NonZeroUsize::new(self.node_cache_capacity).map(|capacity| {
+                Arc::new(Mutex::new(NodeCache {
+                    entries: LruCache::new(capacity),
+                    generation: 0,
+                }))
+            }),
+            codec: self.codec,
+            #[cfg(feature = "metrics")]
+            latencies: Arc::new(Mutex::new(HashMap::new())),
         };
 
-        let families = families();
-        for family in families {
+        // Check before creating anything, so a strict open that fails leaves
+        // the database as it found it. `__meta__` counts as a required family.
+        if graph.strict_families {
+            let mut required = std::iter::once(META_FAMILY).chain(families());
+            if let Some(family) = required.find(|family| graph.db.cf_handle(family).is_none()) {
+                return Err(GraphError::MissingFamilyError(family.to_string()));
+            }
+        }
+
+        graph.create_family_if_not_exists(META_FAMILY)?;
+        graph.store_storage_format()?;
+        for family in families() {
             graph.create_family_if_not_exists(family)?;
         }
 
         Ok(graph)
     }
+}
 
-    pub fn add_node<T>(&self, node: T) -> Result<T, GraphError>
-    where
-        T: Node,
-    {
-        let db = Arc::clone(&self.db);
-        let node_family_name = node.family_name();
+impl FamilyOptions {
+    fn to_options(self, block_cache: &Cache, config: &GraphConfig) -> Options {
+        let mut options = Options::default();
+        let mut block_options = BlockBasedOptions::default();
+        block_options.set_block_cache(block_cache);
+        if let Some(prefix_length) = self.prefix_length {
+            block_options.set_bloom_filter(10.0, false);
+            options.set_prefix_extractor(SliceTransform::create_fixed_prefix(prefix_length));
+            options.set_memtable_prefix_bloom_ratio(0.1);
+        }
+        options.set_block_based_table_factory(&block_options);
+        options.set_write_buffer_size(config.write_buffer_size);
+        options.set_compression_type(self.compression.unwrap_or(config.compression).into());
+        if let Some(compaction_style) = self.compaction_style {
+            options.set_compaction_style(compaction_style);
+        }
+        if let Some(trigger) = self.level_zero_file_num_compaction_trigger {
+            options.set_level_zero_file_num_compaction_trigger(trigger);
+        }
+        options
+    }
 
-        let node_family = db
-            .cf_handle(&node_family_name)
-            .ok_or(GraphError::FindFamilyError)?;
+    fn recommended_for(mut self, family_name: &str) -> FamilyOptions {
+        if self.prefix_length.is_none() {
+            self.prefix_length = family_prefix_length(family_name);
+        }
+        self
+    }
+}
 
-        let txn: Transaction<TransactionDB<MultiThreaded>> = db.transaction();
-        txn.put_cf(
-            &node_family,
-            node.id().to_string(),
-            rmp_serde::to_vec(&node)?,
-        )
-        .map_err(GraphError::CreateNodeError)?;
+#[cfg(feature = "metrics")]
+impl Drop for LatencyTimer<'_> {
+    fn drop(&mut self) {
+        self.graph
+            .latencies
+            .lock()
+            .unwrap()
+            .entry(self.operation)
+            .or_default()
+            .record(self.started.elapsed());
+    }
+}
+
+impl Default for GraphConfig {
+    fn default() -> Self {
+        GraphConfig {
+            parallelism: 1,
+            write_buffer_size: DEFAULT_WRITE_BUFFER_SIZE,
+            max_open_files: -1,
+            compression: Compression::Snappy,
+        }
+    }
+}
+
+impl From<Compression> for DBCompressionType {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::None => DBCompressionType::None,
+            Compression::Snappy => DBCompressionType::Snappy,
+            Compression::Lz4 => DBCompressionType::Lz4,
+            Compression::Zstd => DBCompressionType::Zstd,
+        }
+    }
+}
+
+impl<'a> GraphTxn<'a> {
+    pub fn add_node<T: Node>(&self, node: T) -> Result<T, GraphError> {
+        let node_family = self.graph.family_handle_or_create(T::FAMILY)?;
 
-        txn.commit().map_err(GraphError::CreateNodeError)?;
+        self.graph.reindex_node(&self.txn, &node)?;
+        self.txn
+            .put_cf(
+                &node_family,
+                node.id().to_string(),
+                self.graph.codec.encode(&node)?,
+            )
+            .map_err(rocks(Operation::CreateNode))?;
+        self.written_ids.borrow_mut().push(node.id().to_string());
         Ok(node)
     }
 
-    pub fn get_node<T>(&self, node_id: String) -> Result<T, GraphError>
-    where
-        T: Node,
-    {
-        let db = Arc::clone(&self.db);
-        let node_family_name = node_id
-            .split(':')
-            .next()
-            .ok_or(GraphError::ParseNodeIdError)?;
-        let node_family = db
-            .cf_handle(node_family_name)
-            .ok_or(GraphError::FindFamilyError)?;
-        let value = db
-            .get_cf(&node_family, node_id)
-            .map_err(GraphError::ReadNodeError)?;
+    pub fn add_edge<T: Edge>(&self, edge: T) -> Result<T, GraphError> {
+        edge.connection()
+            .validate()
+            .map_err(GraphError::InvalidConnection)?;
+        let edge_family_name = T::FAMILY;
+        let edge_family = self.graph.family_handle_or_create(edge_family_name)?;
+        let (from_node_id, to_node_id) = edge.connection().endpoints();
+        let edge_id =
+            serde_json::json!({ format!("{}Id", edge_family_name): edge.id().to_string() });
 
-        match value {
-            Some(value) => {
-                let node_payload = rmp_serde::from_slice::<T>(&value)?;
-                Ok(node_payload)
-            }
-            None => Err(GraphError::FindKeyError),
+        // Both endpoints must exist before the edge is written.
+        for node_id in [&from_node_id, &to_node_id] {
+            self.lock_node(node_id)?;
+        }
+        self.txn
+            .put_cf(
+                &edge_family,
+                edge.id().to_string(),
+                self.graph.codec.encode(&edge)?,
+            )
+            .map_err(rocks(Operation::CreateEdge))?;
+        let (from_fields, to_fields) = linked_fields(edge_family_name);
+        for (node_id, fields) in [(&from_node_id, from_fields), (&to_node_id, to_fields)] {
+            self.edit_node_value(node_id, |node| {
+                for field in fields {
+                    node.get_mut(field)
+                        .and_then(Value::as_array_mut)
+                        .ok_or_else(|| {
+                            GraphError::InvalidNodeError(format!("{} has no {}", node_id, field))
+                        })?
+                        .push(edge_id.clone());
+                }
+                Ok(())
+            })?;
         }
+        Ok(edge)
+    }
+
+    pub fn remove_edge<R: Edge>(&self, edge_id: R::Id) -> Result<(), GraphError> {
+        let edge_family = self
+            .graph
+            .db
+            .cf_handle(&edge_id.family_name())
+            .ok_or(GraphError::EdgeFamilyError)?;
+        let value = self
+            .txn
+            .get_for_update_cf(&edge_family, edge_id.to_string(), true)
+            .map_err(rocks(Operation::ReadNode))?
+            .ok_or(GraphError::FindKeyError)?;
+        let edge = self.graph.codec.decode::<R>(&value)?;
+        let (from_node_id, to_node_id) = edge.connection().endpoints();
+
+        self.txn
+            .delete_cf(&edge_family, edge_id.to_string())
+            .map_err(rocks(Operation::Delete))?;
+        for node_id in [&from_node_id, &to_node_id] {
+            self.edit_node_value(node_id, |node| {
+                strip_edge_id(node, &edge_id.to_string());
+                Ok(())
+            })?;
+        }
+        Ok(())
     }
 
     pub fn remove_node(&self, node_id: &str) -> Result<(), GraphError> {
-        let db = Arc::clone(&self.db);
-        let node_family_name = node_id
-            .split(':')
-            .next()
-            .ok_or(GraphError::ParseNodeIdError)?;
-        let node_family = db
+        let node_id = self.graph.resolve_id(node_id);
+        let node_family_name = family_of(&node_id)?;
+        let node_family = self
+            .graph
+            .db
             .cf_handle(node_family_name)
             .ok_or(GraphError::FindFamilyError)?;
 
-        let txn = db.transaction();
-        txn.delete_cf(&node_family, node_id)
-            .map_err(GraphError::DeleteNodeError)?;
-        txn.commit().map_err(GraphError::DeleteNodeError)?;
+        if !indexed_fields(node_family_name).is_empty() {
+            let stored = self.graph.get_value_in_txn(&self.txn, &node_id)?;
+            self.graph
+                .update_indexes(&self.txn, &node_id, stored.as_ref(), None)?;
+        }
+        self.txn
+            .delete_cf(&node_family, node_id.as_bytes())
+            .map_err(rocks(Operation::DeleteNode))?;
+        self.written_ids.borrow_mut().push(node_id.into_owned());
         Ok(())
     }
 
     pub fn update_node<T: Node>(&self, node: &T) -> Result<(), GraphError> {
-        let db = Arc::clone(&self.db);
-        let node_family = node.family_name();
-        let node_family = db
-            .cf_handle(&node_family)
-            .ok_or(GraphError::FindFamilyError)?;
+        let node_family = self.graph.family_handle_or_create(T::FAMILY)?;
 
-        let serialized_node = rmp_serde::to_vec(node)?;
-        self.db
-            .put_cf(&node_family, node.id().to_string(), serialized_node)
-            .map_err(GraphError::UpdateNodeError)?;
+        self.graph.reindex_node(&self.txn, node)?;
+        self.txn
+            .put_cf(
+                &node_family,
+                node.id().to_string(),
+                self.graph.codec.encode(node)?,
+            )
+            .map_err(rocks(Operation::UpdateNode))?;
+        self.written_ids.borrow_mut().push(node.id().to_string());
         Ok(())
     }
 
-    // pub fn add_edge<T, S, R>(&self, edge: T) -> Result<(), GraphError>
-    // where
-    //     T: Edge,
-    //     S: Node,
-    //     R: Node,
-    // {
-    //     let db = Arc::clone(&self.db);
-    //     let edge_family_name = edge.family_name();
-    //     let edge_family = self
-    //         .db
-    //         .cf_handle(&edge_family_name)
-    //         .ok_or(GraphError::EdgeFamilyError)?;
-
-    //     let txn = db.transaction();
-    //     txn.put_cf(
-    //         &edge_family,
-    //         edge.id().to_string(),
-    //         rmp_serde::to_vec(&edge)?,
-    //     )
-    //     .map_err(GraphError::CreateEdgeError)?;
-
-    //     let connection = edge.connection();
-    //     from_node.add_out_connection(connection.clone());
-
-    //     from_node.add_out_edge_id(edge.id().to_string());
-    //     to_node.add_in_edge_id(edge.id().to_string());
-
-    //     self.update_node(&from_node)?;
-    //     self.update_node(&to_node)?;
-
-    //     txn.commit().map_err(GraphError::CreateEdgeError)?;
-    //     Ok(())
-    // }
+    /// Locks `node_id` for the rest of the transaction, failing with
+    /// `FindKeyError` if it doesn't exist.
+    fn lock_node(&self, node_id: &str) -> Result<(), GraphError> {
+        let node_id = self.graph.resolve_id(node_id);
+        let node_family = self
+            .graph
+            .db
+            .cf_handle(family_of(&node_id)?)
+            .ok_or(GraphError::FindFamilyError)?;
+        self.txn
+            .get_for_update_cf(&node_family, node_id.as_bytes(), true)
+            .map_err(rocks(Operation::ReadNode))?
+            .ok_or(GraphError::FindKeyError)?;
+        Ok(())
+    }
 
-    pub fn get_edge<T, R>(&self, edge_id: T) -> Result<R, GraphError>
+    fn edit_node_value<F>(&self, node_id: &str, edit: F) -> Result<(), GraphError>
     where
-        T: EdgeId,
-        R: Edge,
+        F: FnOnce(&mut Value) -> Result<(), GraphError>,
     {
-        let db = Arc::clone(&self.db);
-        let edge_family_name = edge_id.family_name();
-        let edge_family = db
-            .cf_handle(&edge_family_name)
-            .ok_or(GraphError::EdgeFamilyError)?;
+        let node_id = self.graph.resolve_id(node_id);
+        let node_family = self
+            .graph
+            .db
+            .cf_handle(family_of(&node_id)?)
+            .ok_or(GraphError::FindFamilyError)?;
+        let value = self
+            .txn
+            .get_for_update_cf(&node_family, node_id.as_bytes(), true)
+            .map_err(rocks(Operation::ReadNode))?
+            .ok_or(GraphError::FindKeyError)?;
 
-        let value = db
-            .get_cf(&edge_family, edge_id.to_string())
-            .map_err(GraphError::ReadNodeError)?;
+        let mut node = self.graph.codec.decode::<Value>(&value)?;
+        edit(&mut node)?;
+        self.graph.put_value_in_txn(&self.txn, &node_id, &node)?;
+        self.written_ids.borrow_mut().push(node_id.into_owned());
+        Ok(())
+    }
+
+    pub fn commit(self) -> Result<(), GraphError> {
+        self.txn.commit().map_err(rocks(Operation::Commit))?;
+        for node_id in self.written_ids.borrow().iter() {
+            self.graph.uncache_node(node_id);
+        }
+        Ok(())
+    }
+
+    pub fn rollback(self) -> Result<(), GraphError> {
+        self.txn.rollback().map_err(rocks(Operation::Rollback))
+    }
+}
+
+impl<'a> GraphSnapshot<'a> {
+    pub fn get_node<T: Node>(&self, node_id: &str) -> Result<T, GraphError> {
+        let node_id = self.graph.resolve_id(node_id);
+        let node_family_name = family_of(&node_id)?;
+        check_family::<T>(node_family_name)?;
+        let node_family = self
+            .graph
+            .db
+            .cf_handle(node_family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+        let value = self
+            .snapshot
+            .get_cf(&node_family, node_id.as_bytes())
+            .map_err(rocks(Operation::ReadNode))?;
 
         match value {
-            Some(value) => {
-                let edge_payload = rmp_serde::from_slice::<R>(&value)?;
-                Ok(edge_payload)
-            }
+            Some(value) => self.graph.codec.decode::<T>(&value),
             None => Err(GraphError::FindKeyError),
         }
     }
 
-    pub fn remove_edge<T, R>(self, edge_id: T) -> Result<(), GraphError>
+    pub fn get_edge<T, R>(&self, edge_id: T) -> Result<R, GraphError>
     where
         T: EdgeId,
         R: Edge,
     {
-        let db = Arc::clone(&self.db);
-        let edge_family_name = edge_id.family_name();
         let edge_family = self
+            .graph
             .db
-            .cf_handle(&edge_family_name)
+            .cf_handle(&edge_id.family_name())
             .ok_or(GraphError::EdgeFamilyError)?;
+        let value = self
+            .snapshot
+            .get_cf(&edge_family, edge_id.to_string())
+            .map_err(rocks(Operation::ReadNode))?;
+
+        match value {
+            Some(value) => self.graph.codec.decode::<R>(&value),
+            None => Err(GraphError::FindKeyError),
+        }
+    }
 
-        let edge = self.get_edge::<T, R>(edge_id)?;
-        let from_node_id = edge.connection();
+    pub fn get_neighbors<T: Node>(&self, node_id: &str) -> Result<Vec<String>, GraphError> {
+        let node = self.get_node::<T>(node_id)?;
+        Ok(self
+            .graph
+            .out_neighbours(Some(&self.snapshot), &node_to_value(&node)?))
+    }
 
-        let txn = db.transaction();
+    pub fn bfs<T: Node>(
+        &self,
+        start_id: &str,
+        max_depth: usize,
+    ) -> Result<Vec<(String, usize)>, GraphError> {
+        self.graph
+            .bfs_in_snapshot::<T>(Some(&self.snapshot), start_id, max_depth)
+    }
 
-        txn.delete_cf(&edge_family, edge.id().to_string())
-            .map_err(GraphError::DeleteError)?;
-        txn.commit().map_err(GraphError::DeleteError)?;
-        Ok(())
+    pub fn shortest_path(
+        &self,
+        from_id: &str,
+        to_id: &str,
+    ) -> Result<Option<Vec<String>>, GraphError> {
+        self.graph
+            .shortest_path_in_snapshot(Some(&self.snapshot), from_id, to_id)
+    }
+
+    pub fn follow<S, T>(
+        &self,
+        start_id: &str,
+        edge_family: &str,
+        target_edge_family: &str,
+    ) -> Result<Vec<T>, GraphError>
+    where
+        S: Node,
+        T: Node,
+    {
+        self.graph.follow_in_snapshot::<S, T>(
+            Some(&self.snapshot),
+            start_id,
+            edge_family,
+            target_edge_family,
+        )
     }
+}
 
-    // pub fn get_adjacents<T>(&self, node_id: &str) -> Result<Vec<String>, GraphError>
-    // where T: IceNode {
-    // 	let node_family_name = node_id.split(":").next().ok_or(GraphError::NodeFamilyError)?;
-    // 	let node_family = self.db.cf_handle(&node_family_name).ok_or(GraphError::NodeFamilyError)?;
+impl Graph {
+    pub fn new(path: &str) -> Result<Graph, GraphError> {
+        Graph::with_options(path, GraphConfig::default())
+    }
 
-    // 	let node_payload: Result<T, GraphError> = match self.db.get_cf(&node_family, &node_id) {
-    // 			Ok(Some(value)) => {
-    // 				let node_payload = serde_json::from_slice::<T>(&value)?;
-    // 				Ok(node_payload)
-    // 			}
-    // 			Ok(None) => Err(GraphError::FindKeyError),
-    // 			Err(_) => Err(GraphError::FindKeyError),
-    // 	};
-    // 	Ok(node_payload?.nbs().to_vec())
-    // }
+    pub fn with_options(path: &str, config: GraphConfig) -> Result<Graph, GraphError> {
+        GraphBuilder::new(path).config(config).open()
+    }
 
-    fn create_family_if_not_exists(&self, family_name: &str) -> Result<(), GraphError> {
-        let db = &self.db;
-        if db.cf_handle(family_name).is_none() {
-            let options = Options::default();
-            db.create_cf(family_name, &options)
-                .map_err(GraphError::CreateFamilyError)?;
-        }
-        Ok(())
+    pub fn with_codec(path: &str, codec: impl Into<StorageFormat>) -> Result<Graph, GraphError> {
+        GraphBuilder::new(path).codec(codec).open()
     }
 
-    pub fn destroy_everything(&self) -> Result<(), GraphError> {
-        let families =
-            DB::list_cf(&Options::default(), &self.path).map_err(GraphError::FindFamiliesError)?;
+    pub fn builder(path: &str) -> GraphBuilder {
+        GraphBuilder::new(path)
+    }
 
-        for family_name in families {
-            if family_name != "default" {
-                self.db
-                    .drop_cf(&family_name)
-                    .map_err(GraphError::DeleteError)?;
-            }
+    pub fn transaction(&self) -> GraphTxn<'_> {
+        GraphTxn {
+            graph: self,
+            txn: self.db.transaction(),
+            written_ids: RefCell::new(Vec::new()),
         }
-
-        Ok(())
     }
 
-    pub fn display_family_head<T>(&self) -> Result<(), GraphError>
+    pub fn add_node<T>(&self, node: T) -> Result<T, GraphError>
     where
         T: Node,
     {
-        let node_families =
-            DB::list_cf(&Options::default(), &self.path).map_err(GraphError::FindFamiliesError)?;
-        for node_family_name in node_families {
-            let node_family = self
-                .db
-                .cf_handle(&node_family_name)
-                .ok_or(GraphError::NodeFamilyError)?;
+        let _timer = self.time("add_node");
+        let txn = self.transaction();
+        let node = txn.add_node(node)?;
+        txn.commit()?;
+        Ok(node)
+    }
 
-            let records = self
-                .db
-                .iterator_cf(&node_family, rocksdb::IteratorMode::Start);
+    pub fn add_node_get_id<T>(&self, node: T) -> Result<T::Id, GraphError>
+    where
+        T: Node,
+    {
+        let node = self.add_node(node)?;
+        Ok(node.id().clone())
+    }
 
-            println!("Node family: {}", node_family_name);
+    pub fn add_nodes<T>(&self, nodes: Vec<T>) -> Result<(), GraphError>
+    where
+        T: Node,
+    {
+        let _timer = self.time("add_nodes");
+        let db = Arc::clone(&self.db);
+        let node_family = self.family_handle_or_create(T::FAMILY)?;
 
-            for record in records.take(5) {
-                match record {
-                    Ok((key, value)) => {
-                        let key_str =
-                            String::from_utf8(key.to_vec()).map_err(GraphError::ParseUtf8Error)?;
-                        let value_str: T = rmp_serde::from_slice(&value)?;
-                        println!("{}: {:?}", key_str, value_str)
-                    }
-                    Err(_) => return Err(GraphError::FindKeyError),
-                }
-            }
+        let txn = db.transaction();
+        for node in &nodes {
+            self.reindex_node(&txn, node)?;
+            txn.put_cf(
+                &node_family,
+                node.id().to_string(),
+                self.codec.encode(node)?,
+            )
+            .map_err(rocks(Operation::CreateNode))?;
         }
 
+        txn.commit().map_err(rocks(Operation::Commit))?;
+        for node in &nodes {
+            self.uncache_node(&node.id().to_string());
+        }
         Ok(())
     }
 
-    pub fn count_nodes(&self) -> Result<usize, GraphError> {
-        let families =
-            DB::list_cf(&Options::default(), &self.path).map_err(GraphError::FindFamiliesError)?;
+    pub fn reserve_ids<T>(&self, count: usize) -> Vec<T::Id>
+    where
+        T: Node,
+    {
+        (0..count).map(|_| T::Id::new(None)).collect()
+    }
+
+    pub fn get_node<T>(&self, node_id: String) -> Result<T, GraphError>
+    where
+        T: Node,
+    {
+        let _timer = self.time("get_node");
+        let db = Arc::clone(&self.db);
+        let node_id = self.resolve_id(&node_id);
+        let node_family_name = family_of(&node_id)?;
+        check_family::<T>(node_family_name)?;
+        if let Some(value) = self.cached_node(&node_id) {
+            return self.codec.decode::<T>(&value);
+        }
+        let generation = self.node_cache_generation();
+        let node_family = db
+            .cf_handle(node_family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+        let value = db
+            .get_cf(&node_family, node_id.as_bytes())
+            .map_err(rocks(Operation::ReadNode))?;
+
+        match value {
+            Some(value) => {
+                self.cache_node(&node_id, &value, generation);
+                let node_payload = self.codec.decode::<T>(&value)?;
+                Ok(node_payload)
+            }
+            None => Err(GraphError::FindKeyError),
+        }
+    }
+
+    /// Like `get_node`, but a missing node is `Ok(None)` rather than an error.
+    pub fn try_get_node<T: Node>(&self, node_id: &str) -> Result<Option<T>, GraphError> {
+        match self.get_node::<T>(node_id.to_string()) {
+            Ok(node) => Ok(Some(node)),
+            Err(GraphError::FindKeyError) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn get_any_node(&self, node_id: &str) -> Result<AnyNode, GraphError> {
+        let node_id = self.resolve_id(node_id);
+        let node_family_name = family_of(&node_id)?;
+        let node_family = self
+            .db
+            .cf_handle(node_family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+        let value = self
+            .db
+            .get_cf(&node_family, node_id.as_bytes())
+            .map_err(rocks(Operation::ReadNode))?
+            .ok_or(GraphError::FindKeyError)?;
+
+        match AnyNode::decode(&self.codec, node_family_name, &value) {
+            Some(node) => Ok(node?),
+            None => Err(GraphError::NodeFamilyError),
+        }
+    }
+
+    pub fn get_any_edge(&self, edge_id: &str) -> Result<AnyEdge, GraphError> {
+        let edge_id = &*self.resolve_id(edge_id);
+        let edge_family_name = family_of(edge_id)?;
+        let edge_family = self
+            .db
+            .cf_handle(edge_family_name)
+            .ok_or(GraphError::EdgeFamilyError)?;
+        let value = self
+            .db
+            .get_cf(&edge_family, edge_id.as_bytes())
+            .map_err(rocks(Operation::ReadNode))?
+            .ok_or(GraphError::FindKeyError)?;
+
+        match AnyEdge::decode(&self.codec, edge_family_name, &value) {
+            Some(edge) => Ok(edge?),
+            None => Err(GraphError::EdgeFamilyError),
+        }
+    }
+
+    pub fn in_degree(&self, node_id: &str) -> Result<usize, GraphError> {
+        Ok(self.get_any_node(node_id)?.in_degree())
+    }
+
+    pub fn out_degree(&self, node_id: &str) -> Result<usize, GraphError> {
+        Ok(self.get_any_node(node_id)?.out_degree())
+    }
+
+    /// Counts the node's distinct edges, so an undirected edge counts once
+    /// although it adds to both `in_degree` and `out_degree`.
+    pub fn degree(&self, node_id: &str) -> Result<usize, GraphError> {
+        let node = self.get_value(node_id)?.ok_or(GraphError::FindKeyError)?;
+        Ok(edge_ids_of(&node).len())
+    }
+
+    pub fn node_exists(&self, node_id: &str) -> Result<bool, GraphError> {
+        let node_id = self.resolve_id(node_id);
+        if self.cached_node(&node_id).is_some() {
+            return Ok(true);
+        }
+        let node_family = match self.db.cf_handle(family_of(&node_id)?) {
+            Some(node_family) => node_family,
+            None => return Ok(false),
+        };
+        let value = self
+            .db
+            .get_cf(&node_family, node_id.as_bytes())
+            .map_err(rocks(Operation::ReadNode))?;
+        Ok(value.is_some())
+    }
+
+    pub fn get_node_for_update<T>(&self, txn: &GraphTxn, node_id: &str) -> Result<T, GraphError>
+    where
+        T: Node,
+    {
+        let node_id = self.resolve_id(node_id);
+        let node_family_name = family_of(&node_id)?;
+        check_family::<T>(node_family_name)?;
+        let node_family = self
+            .db
+            .cf_handle(node_family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+        let value = txn
+            .txn
+            .get_for_update_cf(&node_family, node_id.as_bytes(), true)
+            .map_err(rocks(Operation::ReadNode))?;
+
+        match value {
+            Some(value) => {
+                let node_payload = self.codec.decode::<T>(&value)?;
+                Ok(node_payload)
+            }
+            None => Err(GraphError::FindKeyError),
+        }
+    }
+
+    /// Loads many nodes with one `multi_get` per family. The result follows the
+    /// order of `node_ids`, with `None` for ids that are not stored.
+    pub fn get_nodes<T: Node>(&self, node_ids: &[String]) -> Result<Vec<Option<T>>, GraphError> {
+        for node_id in node_ids {
+            check_family::<T>(family_of(&self.resolve_id(node_id))?)?;
+        }
+        self.multi_get_values(node_ids)?
+            .into_iter()
+            .map(|value| match value {
+                Some(value) => Ok(Some(self.codec.decode::<T>(&value)?)),
+                None => Ok(None),
+            })
+            .collect()
+    }
+
+    /// Like `get_nodes`, for ids that may belong to any node family.
+    pub fn get_any_nodes(&self, node_ids: &[String]) -> Result<Vec<Option<AnyNode>>, GraphError> {
+        let values = self.multi_get_values(node_ids)?;
+        node_ids
+            .iter()
+            .zip(values)
+            .map(|(node_id, value)| {
+                let value = match value {
+                    Some(value) => value,
+                    None => return Ok(None),
+                };
+                match AnyNode::decode(&self.codec, family_of(&self.resolve_id(node_id))?, &value) {
+                    Some(node) => Ok(Some(node?)),
+                    None => Err(GraphError::NodeFamilyError),
+                }
+            })
+            .collect()
+    }
+
+    fn multi_get_values(&self, node_ids: &[String]) -> Result<Vec<Option<Vec<u8>>>, GraphError> {
+        let db = Arc::clone(&self.db);
+
+        let mut groups: BTreeMap<String, Vec<(usize, String)>> = BTreeMap::new();
+        for (position, node_id) in node_ids.iter().enumerate() {
+            let node_id = self.resolve_id(node_id);
+            groups
+                .entry(family_of(&node_id)?.to_string())
+                .or_default()
+                .push((position, node_id.into_owned()));
+        }
+
+        let mut values = vec![None; node_ids.len()];
+        for (family_name, keys) in groups {
+            let node_family = match db.cf_handle(&family_name) {
+                Some(node_family) => node_family,
+                None => continue,
+            };
+            let found = db.multi_get_cf(keys.iter().map(|(_, key)| (&node_family, key)));
+            for ((position, _), value) in keys.iter().zip(found) {
+                values[*position] = value.map_err(rocks(Operation::ReadNode))?;
+            }
+        }
+
+        Ok(values)
+    }
+
+    pub fn prefetch(&self, node_ids: &[String]) -> Result<(), GraphError> {
+        let db = Arc::clone(&self.db);
+
+        let mut keys = Vec::new();
+        for node_id in node_ids {
+            let node_id = self.resolve_id(node_id);
+            let node_family = db
+                .cf_handle(family_of(&node_id)?)
+                .ok_or(GraphError::FindFamilyError)?;
+            keys.push((node_family, node_id.into_owned()));
+        }
+
+        for value in db.multi_get_cf(keys.iter().map(|(family, key)| (family, key))) {
+            value.map_err(rocks(Operation::ReadNode))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_all_nodes<T>(&self) -> Result<Vec<T>, GraphError>
+    where
+        T: Node,
+    {
+        let mut nodes = self.fold_nodes(Vec::new(), |mut nodes: Vec<T>, node| {
+            nodes.push(node);
+            nodes
+        })?;
+        nodes.sort_by_cached_key(|node| node.sort_key());
+        Ok(nodes)
+    }
+
+    pub fn iter_nodes<T>(
+        &self,
+        family_name: &str,
+    ) -> Result<impl Iterator<Item = Result<T, GraphError>> + '_, GraphError>
+    where
+        T: Node,
+    {
+        let node_family = self
+            .db
+            .cf_handle(family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+
+        Ok(self
+            .db
+            .iterator_cf(&node_family, rocksdb::IteratorMode::Start)
+            .map(|record| {
+                let (_, value) = record.map_err(rocks(Operation::ReadNode))?;
+                self.codec.decode::<T>(&value)
+            }))
+    }
+
+    pub fn scan_nodes<T>(
+        &self,
+        family_name: &str,
+        start_after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<T>, GraphError>
+    where
+        T: Node,
+    {
+        let db = Arc::clone(&self.db);
+        let node_family = db
+            .cf_handle(family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+        let mode = match start_after {
+            Some(cursor) => {
+                rocksdb::IteratorMode::From(cursor.as_bytes(), rocksdb::Direction::Forward)
+            }
+            None => rocksdb::IteratorMode::Start,
+        };
+
+        let mut nodes = Vec::new();
+        for record in db.iterator_cf(&node_family, mode) {
+            let (key, value) = record.map_err(rocks(Operation::ReadNode))?;
+            if start_after.map(str::as_bytes) == Some(&*key) {
+                continue;
+            }
+            if nodes.len() == limit {
+                break;
+            }
+            nodes.push(self.codec.decode::<T>(&value)?);
+        }
+
+        Ok(nodes)
+    }
+
+    /// Nodes whose id starts with `prefix`, e.g. `"Song:cf"`. Seeks straight
+    /// to the prefix rather than scanning the whole family.
+    pub fn nodes_with_id_prefix<T>(
+        &self,
+        family_name: &str,
+        prefix: &str,
+    ) -> Result<Vec<T>, GraphError>
+    where
+        T: Node,
+    {
+        check_family::<T>(family_name)?;
+        let db = Arc::clone(&self.db);
+        let node_family = db
+            .cf_handle(family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+
+        let mut nodes = Vec::new();
+        let mode = rocksdb::IteratorMode::From(prefix.as_bytes(), rocksdb::Direction::Forward);
+        for record in db.iterator_cf(&node_family, mode) {
+            let (key, value) = record.map_err(rocks(Operation::ReadNode))?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            nodes.push(self.codec.decode::<T>(&value)?);
+        }
+
+        Ok(nodes)
+    }
+
+    pub fn recent_nodes<T>(&self, n: usize) -> Result<Vec<T>, GraphError>
+    where
+        T: Node,
+    {
+        let db = Arc::clone(&self.db);
+        let node_family_name = T::FAMILY;
+        let node_family = db
+            .cf_handle(node_family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+
+        let mut nodes = Vec::new();
+        for record in db
+            .iterator_cf(&node_family, rocksdb::IteratorMode::End)
+            .take(n)
+        {
+            let (_, value) = record.map_err(rocks(Operation::ReadNode))?;
+            nodes.push(self.codec.decode::<T>(&value)?);
+        }
+
+        Ok(nodes)
+    }
+
+    pub fn fold_nodes<T, A>(&self, init: A, mut f: impl FnMut(A, T) -> A) -> Result<A, GraphError>
+    where
+        T: Node,
+    {
+        let db = Arc::clone(&self.db);
+        let node_family_name = T::FAMILY;
+        let node_family = db
+            .cf_handle(node_family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+
+        let mut acc = init;
+        for record in db.iterator_cf(&node_family, rocksdb::IteratorMode::Start) {
+            let (_, value) = record.map_err(rocks(Operation::ReadNode))?;
+            acc = f(acc, self.codec.decode::<T>(&value)?);
+        }
+
+        Ok(acc)
+    }
+
+    pub fn orphan_nodes<T>(&self) -> Result<Vec<String>, GraphError>
+    where
+        T: Node,
+    {
+        let db = Arc::clone(&self.db);
+        let node_family_name = T::FAMILY;
+        let node_family = db
+            .cf_handle(node_family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+
+        let mut orphans = Vec::new();
+        for record in db.iterator_cf(&node_family, rocksdb::IteratorMode::Start) {
+            let (_, value) = record.map_err(rocks(Operation::ReadNode))?;
+            let node = self.codec.decode::<T>(&value)?;
+            if node.in_edge_ids().is_empty() && node.out_edge_ids().is_empty() {
+                orphans.push(node.id().to_string());
+            }
+        }
+
+        Ok(orphans)
+    }
+
+    pub fn edge_type_counts(
+        &self,
+        node_id: &str,
+    ) -> Result<BTreeMap<String, (usize, usize)>, GraphError> {
+        let node = self.get_value(node_id)?.ok_or(GraphError::FindKeyError)?;
+
+        let mut counts: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        for (field, outgoing) in [("in_edge_ids", false), ("out_edge_ids", true)] {
+            let edge_ids = node.get(field).and_then(Value::as_array);
+            for edge_id in edge_ids.into_iter().flatten().filter_map(edge_id_of) {
+                let entry = counts.entry(family_of(&edge_id)?.to_string()).or_default();
+                match outgoing {
+                    true => entry.1 += 1,
+                    false => entry.0 += 1,
+                }
+            }
+        }
+
+        Ok(counts)
+    }
+
+    pub fn iter_incident_edges(
+        &self,
+        node_id: &str,
+    ) -> Result<impl Iterator<Item = Result<Value, GraphError>> + '_, GraphError> {
+        let node = self.get_value(node_id)?.ok_or(GraphError::FindKeyError)?;
+
+        Ok(edge_ids_of(&node)
+            .into_iter()
+            .filter_map(move |edge_id| self.get_value(&edge_id).transpose()))
+    }
+
+    /// Materializes every node's outgoing neighbour ids into a dedicated family.
+    ///
+    /// The cache is a snapshot: writes made after it is built are not reflected
+    /// until it is rebuilt or dropped with `invalidate_adjacency_cache`. Nodes
+    /// added since the last build fall back to live resolution in `neighbours`.
+    pub fn build_adjacency_cache(&self) -> Result<(), GraphError> {
+        let _timer = self.time("build_adjacency_cache");
+        self.invalidate_adjacency_cache()?;
+        self.create_family_if_not_exists(ADJACENCY_FAMILY)?;
+
+        let db = Arc::clone(&self.db);
+        let adjacency_family = db
+            .cf_handle(ADJACENCY_FAMILY)
+            .ok_or(GraphError::FindFamilyError)?;
+
+        let mut count = 0;
+        let mut txn = db.transaction();
+        for family_name in node_families() {
+            let family = match db.cf_handle(family_name) {
+                Some(family) => family,
+                None => continue,
+            };
+
+            for record in db.iterator_cf(&family, rocksdb::IteratorMode::Start) {
+                let (key, value) = record.map_err(rocks(Operation::ReadNode))?;
+                let node = self.codec.decode::<Value>(&value)?;
+                if node.get("out_edge_ids").is_none() {
+                    continue;
+                }
+
+                let targets = self.live_neighbours(&node)?;
+                txn.put_cf(&adjacency_family, key, self.codec.encode(&targets)?)
+                    .map_err(rocks(Operation::UpdateNode))?;
+                count += 1;
+
+                if count % WRITE_BATCH_SIZE == 0 {
+                    txn.commit().map_err(rocks(Operation::Commit))?;
+                    txn = db.transaction();
+                }
+            }
+        }
+        txn.commit().map_err(rocks(Operation::Commit))?;
+
+        Ok(())
+    }
+
+    pub fn invalidate_adjacency_cache(&self) -> Result<(), GraphError> {
+        if self.db.cf_handle(ADJACENCY_FAMILY).is_some() {
+            self.db
+                .drop_cf(ADJACENCY_FAMILY)
+                .map_err(rocks(Operation::Delete))?;
+        }
+        Ok(())
+    }
+
+    pub fn neighbours(&self, node_id: &str) -> Result<Vec<String>, GraphError> {
+        let node_id = &*self.resolve_id(node_id);
+        if let Some(adjacency_family) = self.db.cf_handle(ADJACENCY_FAMILY) {
+            let cached = self
+                .db
+                .get_cf(&adjacency_family, node_id)
+                .map_err(rocks(Operation::ReadNode))?;
+            if let Some(cached) = cached {
+                return self.codec.decode::<Vec<String>>(&cached);
+            }
+        }
+
+        let node = self.get_value(node_id)?.ok_or(GraphError::FindKeyError)?;
+        self.live_neighbours(&node)
+    }
+
+    pub fn get_neighbors<T: Node>(&self, node_id: &str) -> Result<Vec<String>, GraphError> {
+        let node = self.get_node::<T>(node_id.to_string())?;
+        Ok(self.out_neighbours(None, &node_to_value(&node)?))
+    }
+
+    pub fn out_edges<T: Node, E: Edge>(&self, node_id: &str) -> Result<Vec<E>, GraphError> {
+        self.incident_edges::<T, E>(node_id, "out_edge_ids")
+    }
+
+    pub fn in_edges<T: Node, E: Edge>(&self, node_id: &str) -> Result<Vec<E>, GraphError> {
+        self.incident_edges::<T, E>(node_id, "in_edge_ids")
+    }
+
+    fn incident_edges<T: Node, E: Edge>(
+        &self,
+        node_id: &str,
+        field: &str,
+    ) -> Result<Vec<E>, GraphError> {
+        let node = node_to_value(&self.get_node::<T>(node_id.to_string())?)?;
+        let edge_family_name = E::FAMILY;
+        let edge_family = match self.db.cf_handle(edge_family_name) {
+            Some(edge_family) => edge_family,
+            None => return Ok(Vec::new()),
+        };
+
+        let edge_ids = node.get(field).and_then(Value::as_array);
+        let mut edges = Vec::new();
+        for edge_id in edge_ids.into_iter().flatten().filter_map(edge_id_of) {
+            if family_of(&edge_id)? != edge_family_name {
+                continue;
+            }
+            let value = self
+                .db
+                .get_cf(&edge_family, edge_id.as_bytes())
+                .map_err(rocks(Operation::ReadNode))?;
+            if let Some(value) = value {
+                edges.push(self.codec.decode::<E>(&value)?);
+            }
+        }
+
+        Ok(edges)
+    }
+
+    /// Edges of family `E` from `from_id` to `to_id`, including parallel
+    /// ones. Undirected edges match in either direction.
+    pub fn edges_between<E: Edge>(&self, from_id: &str, to_id: &str) -> Result<Vec<E>, GraphError> {
+        let from_id = self.resolve_id(from_id);
+        let to_id = self.resolve_id(to_id);
+        let node = self.get_value(&from_id)?.ok_or(GraphError::FindKeyError)?;
+        let edge_family = match self.db.cf_handle(E::FAMILY) {
+            Some(edge_family) => edge_family,
+            None => return Ok(Vec::new()),
+        };
+        let undirected = undirected_edge_families().contains(&E::FAMILY);
+
+        let edge_ids = node.get("out_edge_ids").and_then(Value::as_array);
+        let mut edges = Vec::new();
+        for edge_id in edge_ids.into_iter().flatten().filter_map(edge_id_of) {
+            if family_of(&edge_id)? != E::FAMILY {
+                continue;
+            }
+            let value = self
+                .db
+                .get_cf(&edge_family, edge_id.as_bytes())
+                .map_err(rocks(Operation::ReadNode))?;
+            let edge = match value {
+                Some(value) => self.codec.decode::<E>(&value)?,
+                None => continue,
+            };
+            let endpoints = edge.connection().endpoints();
+            let forward = endpoints.0 == from_id && endpoints.1 == to_id;
+            let backward = undirected && endpoints.0 == to_id && endpoints.1 == from_id;
+            if forward || backward {
+                edges.push(edge);
+            }
+        }
+
+        Ok(edges)
+    }
+
+    /// Returns a read-only view of the graph as of now. Reads through it are
+    /// consistent with each other regardless of concurrent writes.
+    pub fn snapshot(&self) -> GraphSnapshot<'_> {
+        GraphSnapshot {
+            graph: self,
+            snapshot: self.db.snapshot(),
+        }
+    }
+
+    pub fn bfs<T: Node>(
+        &self,
+        start_id: &str,
+        max_depth: usize,
+    ) -> Result<Vec<(String, usize)>, GraphError> {
+        self.bfs_in_snapshot::<T>(None, start_id, max_depth)
+    }
+
+    fn bfs_in_snapshot<T: Node>(
+        &self,
+        snapshot: Option<&DbSnapshot>,
+        start_id: &str,
+        max_depth: usize,
+    ) -> Result<Vec<(String, usize)>, GraphError> {
+        let start_id = self.resolve_id(start_id).into_owned();
+        check_family::<T>(family_of(&start_id)?)?;
+        let start = self
+            .get_value_in_snapshot(snapshot, &start_id)?
+            .ok_or(GraphError::FindKeyError)?;
+
+        let mut visited = HashSet::from([start_id.clone()]);
+        let mut order = vec![(start_id, 0)];
+        let mut queue = VecDeque::from([(start, 0)]);
+        while let Some((node, depth)) = queue.pop_front() {
+            if depth >= max_depth {
+                continue;
+            }
+            for neighbour_id in self.out_neighbours(snapshot, &node) {
+                if !visited.insert(neighbour_id.clone()) {
+                    continue;
+                }
+                let neighbour = match self.get_value_in_snapshot(snapshot, &neighbour_id)? {
+                    Some(neighbour) => neighbour,
+                    None => continue,
+                };
+                order.push((neighbour_id, depth + 1));
+                queue.push_back((neighbour, depth + 1));
+            }
+        }
+
+        Ok(order)
+    }
+
+    pub fn shortest_path(
+        &self,
+        from_id: &str,
+        to_id: &str,
+    ) -> Result<Option<Vec<String>>, GraphError> {
+        self.shortest_path_in_snapshot(None, from_id, to_id)
+    }
+
+    fn shortest_path_in_snapshot(
+        &self,
+        snapshot: Option<&DbSnapshot>,
+        from_id: &str,
+        to_id: &str,
+    ) -> Result<Option<Vec<String>>, GraphError> {
+        let from_id = self.resolve_id(from_id).into_owned();
+        let to_id = self.resolve_id(to_id).into_owned();
+        let start = self
+            .get_value_in_snapshot(snapshot, &from_id)?
+            .ok_or(GraphError::FindKeyError)?;
+        if from_id == to_id {
+            return Ok(Some(vec![from_id]));
+        }
+
+        let mut parents: HashMap<String, String> = HashMap::new();
+        let mut visited = HashSet::from([from_id.clone()]);
+        let mut queue = VecDeque::from([(from_id, start)]);
+        while let Some((node_id, node)) = queue.pop_front() {
+            for neighbour_id in self.out_neighbours(snapshot, &node) {
+                if !visited.insert(neighbour_id.clone()) {
+                    continue;
+                }
+                parents.insert(neighbour_id.clone(), node_id.clone());
+
+                if neighbour_id == to_id {
+                    let mut path = vec![neighbour_id];
+                    while let Some(parent) = parents.get(path.last().unwrap()) {
+                        path.push(parent.clone());
+                    }
+                    path.reverse();
+                    return Ok(Some(path));
+                }
+
+                if let Ok(Some(neighbour)) = self.get_value_in_snapshot(snapshot, &neighbour_id) {
+                    queue.push_back((neighbour_id, neighbour));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn get_node_field_bytes(
+        &self,
+        node_id: &str,
+        field: &str,
+    ) -> Result<Option<Vec<u8>>, GraphError> {
+        let db = Arc::clone(&self.db);
+        let node_id = self.resolve_id(node_id);
+        let node_family_name = family_of(&node_id)?;
+        let node_family = db
+            .cf_handle(node_family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+        let value = db
+            .get_cf(&node_family, node_id.as_bytes())
+            .map_err(rocks(Operation::ReadNode))?;
+
+        match value {
+            Some(value) => {
+                let bytes = self.codec.decode_seed(FieldBytes(field), &value)?;
+                Ok(bytes)
+            }
+            None => Err(GraphError::FindKeyError),
+        }
+    }
+
+    /// Deletes only the node record. Edges referencing it, and their ids on the
+    /// neighbouring nodes, are left in place; use `remove_node_cascade` to
+    /// remove those as well.
+    pub fn remove_node(&self, node_id: &str) -> Result<(), GraphError> {
+        let txn = self.transaction();
+        txn.remove_node(node_id)?;
+        txn.commit()
+    }
+
+    /// Deletes the node together with every edge incident to it, stripping those
+    /// edge ids from the nodes at the other end, in a single transaction.
+    pub fn remove_node_cascade<T: Node>(&self, node_id: &str) -> Result<(), GraphError> {
+        let db = Arc::clone(&self.db);
+        let node_id = self.resolve_id(node_id).into_owned();
+        let node_family_name = family_of(&node_id)?;
+        check_family::<T>(node_family_name)?;
+        let node_family = db
+            .cf_handle(node_family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+
+        let txn = db.transaction();
+        let node = self
+            .get_value_in_txn(&txn, &node_id)?
+            .ok_or(GraphError::FindKeyError)?;
+
+        let mut neighbours: HashMap<String, Value> = HashMap::new();
+        for edge_id in edge_ids_of(&node) {
+            let edge = match self.get_value_in_txn(&txn, &edge_id)? {
+                Some(edge) => edge,
+                None => continue,
+            };
+            if let Some((from_id, to_id)) = endpoints_of(&edge) {
+                for far_id in [from_id, to_id] {
+                    if far_id == node_id {
+                        continue;
+                    }
+                    let far = match neighbours.entry(far_id) {
+                        Entry::Occupied(entry) => entry.into_mut(),
+                        Entry::Vacant(entry) => match self.get_value_in_txn(&txn, entry.key())? {
+                            Some(far) => entry.insert(far),
+                            None => continue,
+                        },
+                    };
+                    strip_edge_id(far, &edge_id);
+                }
+            }
+            let edge_family = db
+                .cf_handle(family_of(&edge_id)?)
+                .ok_or(GraphError::EdgeFamilyError)?;
+            txn.delete_cf(&edge_family, &edge_id)
+                .map_err(rocks(Operation::Delete))?;
+        }
+
+        for (far_id, far) in &neighbours {
+            self.put_value_in_txn(&txn, far_id, far)?;
+        }
+        self.update_indexes(&txn, &node_id, Some(&node), None)?;
+        txn.delete_cf(&node_family, node_id.as_bytes())
+            .map_err(rocks(Operation::DeleteNode))?;
+        txn.commit().map_err(rocks(Operation::Commit))?;
+
+        self.uncache_node(&node_id);
+        for far_id in neighbours.keys() {
+            self.uncache_node(far_id);
+        }
+        Ok(())
+    }
+
+    pub fn update_node<T: Node>(&self, node: &T) -> Result<(), GraphError> {
+        let db = Arc::clone(&self.db);
+        let node_family = db.cf_handle(T::FAMILY).ok_or(GraphError::FindFamilyError)?;
+
+        let mut node = node.clone();
+        node.touch();
+
+        let txn = db.transaction();
+        self.reindex_node(&txn, &node)?;
+        let serialized_node = self.codec.encode(&node)?;
+        txn.put_cf(&node_family, node.id().to_string(), serialized_node)
+            .map_err(rocks(Operation::UpdateNode))?;
+        txn.commit().map_err(rocks(Operation::Commit))?;
+        self.uncache_node(&node.id().to_string());
+        Ok(())
+    }
+
+    /// Reads the node under a transaction lock, applies `f` and writes it back,
+    /// so concurrent mutations of the same node serialize instead of clobbering
+    /// each other. Returns the updated node.
+    pub fn mutate_node<T, F>(&self, node_id: &str, f: F) -> Result<T, GraphError>
+    where
+        T: Node,
+        F: FnOnce(&mut T),
+    {
+        let txn = self.transaction();
+        let mut node = self.get_node_for_update::<T>(&txn, node_id)?;
+        f(&mut node);
+        node.touch();
+        txn.update_node(&node)?;
+        txn.commit()?;
+        Ok(node)
+    }
+
+    pub fn replace_node<T: Node>(&self, mut node: T) -> Result<(), GraphError> {
+        let db = Arc::clone(&self.db);
+        let node_id = node.id().to_string();
+        let node_family = db.cf_handle(T::FAMILY).ok_or(GraphError::FindFamilyError)?;
+
+        let txn = db.transaction();
+        let stored = txn
+            .get_for_update_cf(&node_family, &node_id, true)
+            .map_err(rocks(Operation::ReadNode))?
+            .ok_or(GraphError::FindKeyError)?;
+        let stored = self.codec.decode::<Value>(&stored)?;
+
+        node.touch();
+        let mut replacement = node_to_value(&node)?;
+        for field in ["in_edge_ids", "out_edge_ids", "created_at"] {
+            if let (Some(edge_ids), Some(slot)) = (stored.get(field), replacement.get_mut(field)) {
+                *slot = edge_ids.clone();
+            }
+        }
+
+        self.update_indexes(&txn, &node_id, Some(&stored), Some(&replacement))?;
+        txn.put_cf(&node_family, &node_id, self.codec.encode(&replacement)?)
+            .map_err(rocks(Operation::UpdateNode))?;
+        txn.commit().map_err(rocks(Operation::Commit))?;
+        self.uncache_node(&node_id);
+        Ok(())
+    }
+
+    pub fn append_to_field(
+        &self,
+        node_id: &str,
+        field: &str,
+        value: Value,
+    ) -> Result<(), GraphError> {
+        if RESERVED_FIELDS.contains(&field) {
+            return Err(GraphError::UnknownFieldError(field.to_string()));
+        }
+
+        let db = Arc::clone(&self.db);
+        let node_id = self.resolve_id(node_id);
+        let node_family = db
+            .cf_handle(family_of(&node_id)?)
+            .ok_or(GraphError::FindFamilyError)?;
+
+        let txn = db.transaction();
+        let stored = txn
+            .get_for_update_cf(&node_family, node_id.as_bytes(), true)
+            .map_err(rocks(Operation::ReadNode))?
+            .ok_or(GraphError::FindKeyError)?;
+        let stored = self.codec.decode::<Value>(&stored)?;
+        let mut node_value = stored.clone();
+
+        match node_value.get_mut(field) {
+            Some(Value::Array(values)) => values.push(value),
+            Some(_) => {
+                return Err(GraphError::InvalidNodeError(format!(
+                    "field {} is not an array",
+                    field
+                )))
+            }
+            None => return Err(GraphError::UnknownFieldError(field.to_string())),
+        }
+
+        self.update_indexes(&txn, &node_id, Some(&stored), Some(&node_value))?;
+        txn.put_cf(
+            &node_family,
+            node_id.as_bytes(),
+            self.codec.encode(&node_value)?,
+        )
+        .map_err(rocks(Operation::UpdateNode))?;
+        txn.commit().map_err(rocks(Operation::Commit))?;
+        self.uncache_node(&node_id);
+        Ok(())
+    }
+
+    pub fn set_field_all<T>(&self, field: &str, value: Value) -> Result<usize, GraphError>
+    where
+        T: Node,
+    {
+        let _timer = self.time("set_field_all");
+        if RESERVED_FIELDS.contains(&field) {
+            return Err(GraphError::UnknownFieldError(field.to_string()));
+        }
+
+        let db = Arc::clone(&self.db);
+        let node_family_name = T::FAMILY;
+        let node_family = db
+            .cf_handle(node_family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+
+        let mut count = 0;
+        let mut txn = db.transaction();
+        for record in db.iterator_cf(&node_family, rocksdb::IteratorMode::Start) {
+            let (key, bytes) = record.map_err(rocks(Operation::ReadNode))?;
+            let mut node_value = node_to_value(&self.codec.decode::<T>(&bytes)?)?;
+            match node_value.get_mut(field) {
+                Some(field_value) => *field_value = value.clone(),
+                None => return Err(GraphError::UnknownFieldError(field.to_string())),
+            }
+            let node = value_to_node::<T>(&node_value)?;
+            self.reindex_node(&txn, &node)?;
+
+            txn.put_cf(&node_family, key, self.codec.encode(&node)?)
+                .map_err(rocks(Operation::UpdateNode))?;
+            count += 1;
+
+            if count % WRITE_BATCH_SIZE == 0 {
+                txn.commit().map_err(rocks(Operation::Commit))?;
+                txn = db.transaction();
+            }
+        }
+        txn.commit().map_err(rocks(Operation::Commit))?;
+        self.clear_node_cache();
+
+        Ok(count)
+    }
+
+    pub fn find_duplicates<T>(
+        &self,
+        field: &str,
+    ) -> Result<HashMap<String, Vec<String>>, GraphError>
+    where
+        T: Node,
+    {
+        let db = Arc::clone(&self.db);
+        let node_family_name = T::FAMILY;
+        let node_family = db
+            .cf_handle(node_family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+
+        let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+        for record in db.iterator_cf(&node_family, rocksdb::IteratorMode::Start) {
+            let (_, value) = record.map_err(rocks(Operation::ReadNode))?;
+            let node = self.codec.decode::<T>(&value)?;
+            let node_value = node_to_value(&node)?;
+            let field_value = node_value
+                .get(field)
+                .ok_or_else(|| GraphError::UnknownFieldError(field.to_string()))?;
+
+            let key = match field_value {
+                Value::String(field_value) => field_value.clone(),
+                field_value => field_value.to_string(),
+            };
+            groups.entry(key).or_default().push(node.id().to_string());
+        }
+
+        groups.retain(|_, ids| ids.len() > 1);
+        Ok(groups)
+    }
+
+    pub fn find_by<T>(&self, field: &str, value: &str) -> Result<Vec<T>, GraphError>
+    where
+        T: Node,
+    {
+        if !indexed_fields(T::FAMILY).contains(&field) {
+            return Err(GraphError::UnknownFieldError(field.to_string()));
+        }
+        if range_indexed_fields(T::FAMILY).contains(&field) {
+            let number = value
+                .parse::<f64>()
+                .map_err(|_| GraphError::InvalidNodeError(format!("{} is not a number", value)))?;
+            return self.find_in_range::<T>(field, number, number);
+        }
+
+        let db = Arc::clone(&self.db);
+        let index_family = db
+            .cf_handle(&index_family_name(T::FAMILY, field))
+            .ok_or(GraphError::FindFamilyError)?;
+        let prefix = format!("{}:", value);
+
+        let mut nodes = Vec::new();
+        let mode = rocksdb::IteratorMode::From(prefix.as_bytes(), rocksdb::Direction::Forward);
+        for record in db.iterator_cf(&index_family, mode) {
+            let (key, _) = record.map_err(rocks(Operation::ReadNode))?;
+            let node_id = match key.strip_prefix(prefix.as_bytes()) {
+                Some(node_id) => String::from_utf8(node_id.to_vec())?,
+                None => break,
+            };
+            // Values containing ':' can share a prefix with longer values, so
+            // keys that don't resolve to a matching node are skipped.
+            let node = match self.get_node::<T>(node_id) {
+                Ok(node) => node,
+                Err(
+                    GraphError::FindKeyError
+                    | GraphError::FamilyMismatch { .. }
+                    | GraphError::ParseNodeIdError,
+                ) => continue,
+                Err(error) => return Err(error),
+            };
+            match node_to_value(&node)?.get(field) {
+                Some(field_value) if index_value(field_value) == value => nodes.push(node),
+                _ => continue,
+            }
+        }
+
+        Ok(nodes)
+    }
+
+    /// Returns nodes whose indexed numeric field lies in `lo..=hi`, in
+    /// ascending order. Only fields marked `indexed: true` with an integer or
+    /// float type support range queries; values are compared as `f64`, so
+    /// 64-bit integers beyond 2^53 lose precision. Other fields return
+    /// `UnindexableField`.
+    pub fn find_in_range<T>(&self, field: &str, lo: f64, hi: f64) -> Result<Vec<T>, GraphError>
+    where
+        T: Node,
+    {
+        if !range_indexed_fields(T::FAMILY).contains(&field) {
+            return Err(GraphError::UnindexableField(field.to_string()));
+        }
+
+        let db = Arc::clone(&self.db);
+        let index_family = db
+            .cf_handle(&index_family_name(T::FAMILY, field))
+            .ok_or(GraphError::FindFamilyError)?;
+        let start = ordered_f64_bytes(lo);
+        let end = ordered_f64_bytes(hi);
+
+        let mut nodes = Vec::new();
+        let mode = rocksdb::IteratorMode::From(&start, rocksdb::Direction::Forward);
+        for record in db.iterator_cf(&index_family, mode) {
+            let (key, _) = record.map_err(rocks(Operation::ReadNode))?;
+            if key.len() < 8 || key[..8] > end[..] {
+                break;
+            }
+            let node_id = String::from_utf8(key[8..].to_vec())?;
+            nodes.push(self.get_node::<T>(node_id)?);
+        }
+
+        Ok(nodes)
+    }
+
+    pub fn distinct_field_values<T>(&self, field: &str) -> Result<BTreeSet<String>, GraphError>
+    where
+        T: Node,
+    {
+        let db = Arc::clone(&self.db);
+        let node_family_name = T::FAMILY;
+        let node_family = db
+            .cf_handle(node_family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+
+        let mut values = BTreeSet::new();
+        for record in db.iterator_cf(&node_family, rocksdb::IteratorMode::Start) {
+            let (_, value) = record.map_err(rocks(Operation::ReadNode))?;
+            let node_value = node_to_value(&self.codec.decode::<T>(&value)?)?;
+            let field_value = node_value
+                .get(field)
+                .ok_or_else(|| GraphError::UnknownFieldError(field.to_string()))?;
+
+            values.insert(match field_value {
+                Value::String(field_value) => field_value.clone(),
+                field_value => field_value.to_string(),
+            });
+        }
+
+        Ok(values)
+    }
+
+    pub fn merge_nodes(&self, keep_id: &str, merge_ids: &[String]) -> Result<(), GraphError> {
+        let db = Arc::clone(&self.db);
+        let txn = db.transaction();
+        // Edges store canonical ids, so repointing them needs resolved ids.
+        let keep_id = &*self.resolve_id(keep_id);
+        let merge_ids: Vec<String> = merge_ids
+            .iter()
+            .map(|merge_id| self.resolve_id(merge_id).into_owned())
+            .collect();
+
+        let mut keep = self
+            .get_value_in_txn(&txn, keep_id)?
+            .ok_or(GraphError::FindKeyError)?;
+
+        for merge_id in &merge_ids {
+            if merge_id == keep_id {
+                continue;
+            }
+            if family_of(merge_id)? != family_of(keep_id)? {
+                return Err(GraphError::NodeFamilyError);
+            }
+            let merged = self
+                .get_value_in_txn(&txn, merge_id)?
+                .ok_or(GraphError::FindKeyError)?;
+
+            for field in ["in_edge_ids", "out_edge_ids"] {
+                let entries = merged.get(field).and_then(Value::as_array);
+                for entry in entries.into_iter().flatten() {
+                    let edge_id = match edge_id_of(entry) {
+                        Some(edge_id) => edge_id,
+                        None => continue,
+                    };
+                    if let Some(mut edge) = self.get_value_in_txn(&txn, &edge_id)? {
+                        repoint_edge(&mut edge, merge_id, keep_id);
+                        self.put_value_in_txn(&txn, &edge_id, &edge)?;
+                    }
+                    if let Some(Value::Array(keep_entries)) = keep.get_mut(field) {
+                        if !keep_entries.contains(entry) {
+                            keep_entries.push(entry.clone());
+                        }
+                    }
+                }
+            }
+
+            let node_family = db
+                .cf_handle(family_of(merge_id)?)
+                .ok_or(GraphError::FindFamilyError)?;
+            self.update_indexes(&txn, merge_id, Some(&merged), None)?;
+            txn.delete_cf(&node_family, merge_id)
+                .map_err(rocks(Operation::DeleteNode))?;
+        }
+
+        self.put_value_in_txn(&txn, keep_id, &keep)?;
+        txn.commit().map_err(rocks(Operation::Commit))?;
+        self.uncache_node(keep_id);
+        for merge_id in &merge_ids {
+            self.uncache_node(merge_id);
+        }
+        Ok(())
+    }
+
+    pub fn add_edge<T: Edge>(&self, edge: T) -> Result<T, GraphError> {
+        let _timer = self.time("add_edge");
+        let txn = self.transaction();
+        let edge = txn.add_edge(edge)?;
+        txn.commit()?;
+        Ok(edge)
+    }
+
+    /// Adds `edge` unless an edge with the same connection already joins its
+    /// endpoints. In that case the existing edge keeps its id and creation
+    /// time, takes the remaining fields of `edge`, and is returned.
+    pub fn add_edge_unique<E: Edge>(&self, edge: E) -> Result<E, GraphError> {
+        let _timer = self.time("add_edge_unique");
+        let (from_id, to_id) = edge.connection().endpoints();
+        let txn = self.transaction();
+
+        // Locking the source node serialises concurrent upserts of one edge.
+        let from_family = self
+            .db
+            .cf_handle(family_of(&from_id)?)
+            .ok_or(GraphError::FindFamilyError)?;
+        txn.txn
+            .get_for_update_cf(&from_family, from_id.as_bytes(), true)
+            .map_err(rocks(Operation::ReadNode))?;
+
+        let variant = connection_variant(&serde_json::to_value(edge.connection())?);
+        let mut existing = None;
+        for candidate in self.edges_between::<E>(&from_id, &to_id)? {
+            if connection_variant(&serde_json::to_value(candidate.connection())?) == variant {
+                existing = Some(candidate);
+                break;
+            }
+        }
+
+        let edge = match existing {
+            Some(existing) => {
+                let mut value = serde_json::to_value(&edge)?;
+                value["id"] = serde_json::to_value(existing.id())?;
+                if let Some(created_at) = existing.created_at() {
+                    value["created_at"] = created_at.into();
+                }
+                let updated = serde_json::from_value::<E>(value)?;
+                let edge_family = self
+                    .db
+                    .cf_handle(E::FAMILY)
+                    .ok_or(GraphError::EdgeFamilyError)?;
+                txn.txn
+                    .put_cf(
+                        &edge_family,
+                        updated.id().to_string(),
+                        self.codec.encode(&updated)?,
+                    )
+                    .map_err(rocks(Operation::CreateEdge))?;
+                updated
+            }
+            None => txn.add_edge(edge)?,
+        };
+        txn.commit()?;
+        Ok(edge)
+    }
+
+    pub fn add_edges<T>(&self, edges: Vec<T>) -> Result<(), GraphError>
+    where
+        T: Edge,
+    {
+        let _timer = self.time("add_edges");
+        let db = Arc::clone(&self.db);
+        if edges.is_empty() {
+            return Ok(());
+        }
+        let edge_family_name = T::FAMILY;
+        let edge_family = self.family_handle_or_create(edge_family_name)?;
+        let edge_id_variant = format!("{}Id", edge_family_name);
+        let undirected = undirected_edge_families().contains(&edge_family_name);
+
+        let txn = db.transaction();
+        let mut pending: HashMap<String, (Vec<Value>, Vec<Value>)> = HashMap::new();
+        for edge in &edges {
+            edge.connection()
+                .validate()
+                .map_err(GraphError::InvalidConnection)?;
+            txn.put_cf(
+                &edge_family,
+                edge.id().to_string(),
+                self.codec.encode(edge)?,
+            )
+            .map_err(rocks(Operation::CreateEdge))?;
+
+            let (from_id, to_id) = edge.connection().endpoints();
+            let edge_id = serde_json::json!({ &edge_id_variant: edge.id().to_string() });
+            if undirected {
+                pending
+                    .entry(from_id.clone())
+                    .or_default()
+                    .0
+                    .push(edge_id.clone());
+                pending
+                    .entry(to_id.clone())
+                    .or_default()
+                    .1
+                    .push(edge_id.clone());
+            }
+            pending.entry(from_id).or_default().1.push(edge_id.clone());
+            pending.entry(to_id).or_default().0.push(edge_id);
+        }
+
+        for (node_id, (in_edge_ids, out_edge_ids)) in &pending {
+            let mut node = self
+                .get_value_in_txn(&txn, node_id)?
+                .ok_or(GraphError::FindKeyError)?;
+            for (field, edge_ids) in [("in_edge_ids", in_edge_ids), ("out_edge_ids", out_edge_ids)]
+            {
+                node.get_mut(field)
+                    .and_then(Value::as_array_mut)
+                    .ok_or_else(|| {
+                        GraphError::InvalidNodeError(format!("{} has no {}", node_id, field))
+                    })?
+                    .extend(edge_ids.iter().cloned());
+            }
+            self.put_value_in_txn(&txn, node_id, &node)?;
+        }
+
+        txn.commit().map_err(rocks(Operation::Commit))?;
+        for node_id in pending.keys() {
+            self.uncache_node(node_id);
+        }
+        Ok(())
+    }
+
+    pub fn get_edge<T, R>(&self, edge_id: T) -> Result<R, GraphError>
+    where
+        T: EdgeId,
+        R: Edge,
+    {
+        let db = Arc::clone(&self.db);
+        let edge_family_name = edge_id.family_name();
+        let edge_family = db
+            .cf_handle(&edge_family_name)
+            .ok_or(GraphError::EdgeFamilyError)?;
+
+        let value = db
+            .get_cf(&edge_family, edge_id.to_string())
+            .map_err(rocks(Operation::ReadNode))?;
+
+        match value {
+            Some(value) => {
+                let edge_payload = self.codec.decode::<R>(&value)?;
+                Ok(edge_payload)
+            }
+            None => Err(GraphError::FindKeyError),
+        }
+    }
+
+    pub fn edges_created_between<E>(&self, start: i64, end: i64) -> Result<Vec<E>, GraphError>
+    where
+        E: Edge,
+    {
+        let db = Arc::clone(&self.db);
+        let edge_family_name = E::FAMILY;
+        let edge_family = db
+            .cf_handle(edge_family_name)
+            .ok_or(GraphError::EdgeFamilyError)?;
+
+        let mut edges = Vec::new();
+        for record in db.iterator_cf(&edge_family, rocksdb::IteratorMode::Start) {
+            let (_, value) = record.map_err(rocks(Operation::ReadNode))?;
+            let edge = self.codec.decode::<E>(&value)?;
+            if let Some(created_at) = edge.created_at() {
+                if (start..=end).contains(&created_at) {
+                    edges.push(edge);
+                }
+            }
+        }
+
+        Ok(edges)
+    }
+
+    pub fn k_core<E, T>(&self, k: usize) -> Result<Vec<String>, GraphError>
+    where
+        E: Edge,
+        T: Node,
+    {
+        let mut adjacency = self.undirected_adjacency::<E, T>()?;
+
+        let mut queue: VecDeque<String> = adjacency
+            .iter()
+            .filter(|(_, neighbours)| neighbours.len() < k)
+            .map(|(node_id, _)| node_id.clone())
+            .collect();
+        while let Some(node_id) = queue.pop_front() {
+            let neighbours = match adjacency.remove(&node_id) {
+                Some(neighbours) => neighbours,
+                None => continue,
+            };
+            for neighbour_id in neighbours {
+                if let Some(remaining) = adjacency.get_mut(&neighbour_id) {
+                    remaining.remove(&node_id);
+                    if remaining.len() + 1 == k {
+                        queue.push_back(neighbour_id);
+                    }
+                }
+            }
+        }
+
+        Ok(adjacency.into_keys().collect())
+    }
+
+    pub fn follow<S, T>(
+        &self,
+        start_id: &str,
+        edge_family: &str,
+        target_edge_family: &str,
+    ) -> Result<Vec<T>, GraphError>
+    where
+        S: Node,
+        T: Node,
+    {
+        self.follow_in_snapshot::<S, T>(None, start_id, edge_family, target_edge_family)
+    }
+
+    fn follow_in_snapshot<S, T>(
+        &self,
+        snapshot: Option<&DbSnapshot>,
+        start_id: &str,
+        edge_family: &str,
+        target_edge_family: &str,
+    ) -> Result<Vec<T>, GraphError>
+    where
+        S: Node,
+        T: Node,
+    {
+        let start_id = &*self.resolve_id(start_id);
+        check_family::<S>(family_of(start_id)?)?;
+        let start = self
+            .get_value_in_snapshot(snapshot, start_id)?
+            .ok_or(GraphError::FindKeyError)?;
+
+        let mut seen = HashSet::new();
+        let mut targets = Vec::new();
+        for intermediate_id in self.linked_ids(snapshot, &start, start_id, edge_family)? {
+            let intermediate = match self.get_value_in_snapshot(snapshot, &intermediate_id)? {
+                Some(intermediate) => intermediate,
+                None => continue,
+            };
+            let target_ids = self.linked_ids(
+                snapshot,
+                &intermediate,
+                &intermediate_id,
+                target_edge_family,
+            )?;
+            for target_id in target_ids {
+                if family_of(&target_id)? != T::FAMILY || !seen.insert(target_id.clone()) {
+                    continue;
+                }
+                if let Some(target) = self.get_value_in_snapshot(snapshot, &target_id)? {
+                    targets.push(value_to_node::<T>(&target)?);
+                }
+            }
+        }
+
+        Ok(targets)
+    }
+
+    /// Closeness centrality over `E`, treated as undirected, for every node in `T`.
+    ///
+    /// Runs a BFS from each node, so the cost is O(V·(V+E)); intended for modest
+    /// graphs. Nodes that reach nothing score 0.
+    pub fn closeness_centrality<E, T>(&self) -> Result<HashMap<String, f64>, GraphError>
+    where
+        E: Edge,
+        T: Node,
+    {
+        let adjacency = self.undirected_adjacency::<E, T>()?;
+
+        let mut centrality = HashMap::new();
+        for node_id in adjacency.keys() {
+            let mut distances: HashMap<&str, usize> = HashMap::from([(node_id.as_str(), 0)]);
+            let mut queue = VecDeque::from([node_id.as_str()]);
+            while let Some(current_id) = queue.pop_front() {
+                let distance = distances[current_id];
+                for neighbour_id in adjacency.get(current_id).into_iter().flatten() {
+                    if !distances.contains_key(neighbour_id.as_str()) {
+                        distances.insert(neighbour_id, distance + 1);
+                        queue.push_back(neighbour_id);
+                    }
+                }
+            }
+
+            let reachable = distances.len() - 1;
+            let total_distance: usize = distances.values().sum();
+            let score = match total_distance {
+                0 => 0.0,
+                total_distance => reachable as f64 / total_distance as f64,
+            };
+            centrality.insert(node_id.clone(), score);
+        }
+
+        Ok(centrality)
+    }
+
+    /// Labels every node with the index of its weakly connected component,
+    /// treating all edges as undirected.
+    pub fn connected_components(&self) -> Result<HashMap<String, usize>, GraphError> {
+        let adjacency = self.directed_adjacency()?;
+        let positions: HashMap<&str, usize> = adjacency
+            .keys()
+            .enumerate()
+            .map(|(position, node_id)| (node_id.as_str(), position))
+            .collect();
+
+        let mut parents: Vec<usize> = (0..adjacency.len()).collect();
+        for (node_id, targets) in &adjacency {
+            for target_id in targets {
+                let from_root = find_root(&mut parents, positions[node_id.as_str()]);
+                let to_root = find_root(&mut parents, positions[target_id.as_str()]);
+                parents[from_root.max(to_root)] = from_root.min(to_root);
+            }
+        }
+
+        let mut labels: HashMap<usize, usize> = HashMap::new();
+        let mut components = HashMap::new();
+        for (position, node_id) in adjacency.keys().enumerate() {
+            let root = find_root(&mut parents, position);
+            let next_label = labels.len();
+            let label = *labels.entry(root).or_insert(next_label);
+            components.insert(node_id.clone(), label);
+        }
+
+        Ok(components)
+    }
+
+    /// PageRank over every node and edge in the graph, returning scores that sum
+    /// to 1. Nodes without out-edges spread their rank uniformly.
+    ///
+    /// The whole adjacency list is held in memory and each iteration is O(V+E),
+    /// so this is meant for graphs of up to a few million edges.
+    pub fn pagerank(
+        &self,
+        damping: f64,
+        iterations: usize,
+    ) -> Result<HashMap<String, f64>, GraphError> {
+        let adjacency = self.directed_adjacency()?;
+        if adjacency.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let positions: HashMap<&str, usize> = adjacency
+            .keys()
+            .enumerate()
+            .map(|(position, node_id)| (node_id.as_str(), position))
+            .collect();
+        let out_edges: Vec<Vec<usize>> = adjacency
+            .values()
+            .map(|targets| {
+                targets
+                    .iter()
+                    .map(|target_id| positions[target_id.as_str()])
+                    .collect()
+            })
+            .collect();
+
+        let node_count = adjacency.len() as f64;
+        let mut ranks = vec![1.0 / node_count; adjacency.len()];
+        for _ in 0..iterations {
+            let dangling: f64 = out_edges
+                .iter()
+                .zip(&ranks)
+                .filter(|(targets, _)| targets.is_empty())
+                .map(|(_, rank)| rank)
+                .sum();
+            let base = (1.0 - damping) / node_count + damping * dangling / node_count;
+
+            let mut next = vec![base; ranks.len()];
+            for (targets, rank) in out_edges.iter().zip(&ranks) {
+                let share = damping * rank / targets.len() as f64;
+                for &target in targets {
+                    next[target] += share;
+                }
+            }
+
+            let total: f64 = next.iter().sum();
+            ranks = next.into_iter().map(|rank| rank / total).collect();
+        }
+
+        Ok(adjacency.into_keys().zip(ranks).collect())
+    }
+
+    pub fn join<S, E, T>(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<(S, E, T), GraphError>> + '_, GraphError>
+    where
+        S: Node,
+        E: Edge,
+        T: Node,
+    {
+        let edge_family_name = E::FAMILY;
+        let edge_family = self
+            .db
+            .cf_handle(edge_family_name)
+            .ok_or(GraphError::EdgeFamilyError)?;
+
+        let records = self
+            .db
+            .iterator_cf(&edge_family, rocksdb::IteratorMode::Start);
+
+        Ok(records.filter_map(move |record| match record {
+            Ok((_, value)) => self.join_triple(&value).transpose(),
+            Err(source) => Some(Err(GraphError::Rocks {
+                op: Operation::ReadNode,
+                source,
+            })),
+        }))
+    }
+
+    fn join_triple<S, E, T>(&self, value: &[u8]) -> Result<Option<(S, E, T)>, GraphError>
+    where
+        S: Node,
+        E: Edge,
+        T: Node,
+    {
+        let edge = self.codec.decode::<E>(value)?;
+        let (from_id, to_id) = match endpoints_of(&self.codec.decode::<Value>(value)?) {
+            Some(endpoints) => endpoints,
+            None => return Ok(None),
+        };
+        if family_of(&from_id)? != S::FAMILY || family_of(&to_id)? != T::FAMILY {
+            return Ok(None);
+        }
+
+        let source = match self.get_node::<S>(from_id) {
+            Ok(source) => source,
+            Err(GraphError::FindKeyError) => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        let target = match self.get_node::<T>(to_id) {
+            Ok(target) => target,
+            Err(GraphError::FindKeyError) => return Ok(None),
+            Err(error) => return Err(error),
+        };
+
+        Ok(Some((source, edge, target)))
+    }
+
+    pub fn remove_edge<R: Edge>(&self, edge_id: R::Id) -> Result<(), GraphError> {
+        let txn = self.transaction();
+        txn.remove_edge::<R>(edge_id)?;
+        txn.commit()
+    }
+
+    fn family_handle_or_create(
+        &self,
+        family_name: &str,
+    ) -> Result<Arc<BoundColumnFamily<'_>>, GraphError> {
+        if self.db.cf_handle(family_name).is_none() {
+            if self.strict_families {
+                return Err(GraphError::MissingFamilyError(family_name.to_string()));
+            }
+            self.create_family_if_not_exists(family_name)?;
+        }
+        self.db
+            .cf_handle(family_name)
+            .ok_or(GraphError::FindFamilyError)
+    }
+
+    fn create_family_if_not_exists(&self, family_name: &str) -> Result<(), GraphError> {
+        let family_options = self.family_options.recommended_for(family_name);
+        self.create_family_with_options(family_name, family_options)
+    }
+
+    fn create_family_with_options(
+        &self,
+        family_name: &str,
+        family_options: FamilyOptions,
+    ) -> Result<(), GraphError> {
+        let db = &self.db;
+        if db.cf_handle(family_name).is_none() {
+            let options = family_options.to_options(&self.block_cache, &self.config);
+            db.create_cf(family_name, &options)
+                .map_err(rocks(Operation::CreateFamily))?;
+
+            if family_name != META_FAMILY {
+                self.store_family_options(family_name, &family_options)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn store_family_options(
+        &self,
+        family_name: &str,
+        family_options: &FamilyOptions,
+    ) -> Result<(), GraphError> {
+        // Pin the compression the family was created with, so reopening with
+        // a different `GraphConfig` doesn't change it.
+        let family_options = FamilyOptions {
+            compression: Some(
+                family_options
+                    .compression
+                    .unwrap_or(self.config.compression),
+            ),
+            ..*family_options
+        };
+        // Family options stay msgpack whatever the codec, because they are
+        // read before the graph is opened.
+        if let Some(meta_family) = self.db.cf_handle(META_FAMILY) {
+            self.db
+                .put_cf(
+                    &meta_family,
+                    format!("{}{}", FAMILY_OPTIONS_PREFIX, family_name),
+                    rmp_serde::to_vec_named(&family_options)?,
+                )
+                .map_err(rocks(Operation::UpdateNode))?;
+        }
+        Ok(())
+    }
+
+    /// Records the codec the first time the graph is opened. Like family
+    /// options it is always msgpack, so it can be read without knowing it.
+    fn store_storage_format(&self) -> Result<(), GraphError> {
+        let meta_family = self
+            .db
+            .cf_handle(META_FAMILY)
+            .ok_or(GraphError::FindFamilyError)?;
+        let stored = self
+            .db
+            .get_cf(&meta_family, STORAGE_FORMAT_KEY)
+            .map_err(rocks(Operation::ReadNode))?;
+        if stored.is_none() {
+            self.db
+                .put_cf(
+                    &meta_family,
+                    STORAGE_FORMAT_KEY,
+                    rmp_serde::to_vec_named(&self.codec)?,
+                )
+                .map_err(rocks(Operation::UpdateNode))?;
+        }
+        Ok(())
+    }
+
+    /// Sets the column family options for `family_name`. A family that does not
+    /// exist yet is created with them; for an existing family they are stored and
+    /// take effect the next time the graph is opened.
+    pub fn set_family_options(
+        &self,
+        family_name: &str,
+        family_options: FamilyOptions,
+    ) -> Result<(), GraphError> {
+        if family_name == META_FAMILY {
+            return Err(GraphError::NodeFamilyError);
+        }
+        match self.db.cf_handle(family_name) {
+            Some(_) => self.store_family_options(family_name, &family_options),
+            None => self.create_family_with_options(family_name, family_options),
+        }
+    }
+
+    /// The options stored for `family_name`, which are the ones it will be
+    /// opened with next time.
+    pub fn family_options(&self, family_name: &str) -> Result<Option<FamilyOptions>, GraphError> {
+        let meta_family = self
+            .db
+            .cf_handle(META_FAMILY)
+            .ok_or(GraphError::FindFamilyError)?;
+        let stored = self
+            .db
+            .get_cf(
+                &meta_family,
+                format!("{}{}", FAMILY_OPTIONS_PREFIX, family_name),
+            )
+            .map_err(rocks(Operation::ReadNode))?;
+        match stored {
+            Some(stored) => Ok(Some(rmp_serde::from_slice(&stored)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn families_on_disk(&self) -> Result<Vec<String>, GraphError> {
+        DB::list_cf(&Options::default(), &self.path).map_err(rocks(Operation::FindFamilies))
+    }
+
+    pub fn unknown_families(&self) -> Result<Vec<String>, GraphError> {
+        let known = families();
+        let unknown = self
+            .families_on_disk()?
+            .into_iter()
+            .filter(|family_name| family_name != "default" && !is_internal_family(family_name))
+            .filter(|family_name| !known.contains(&family_name.as_str()))
+            .collect();
+
+        Ok(unknown)
+    }
+
+    pub fn close(self) -> Result<(), GraphError> {
+        match Arc::try_unwrap(self.db) {
+            Ok(db) => {
+                drop(db);
+                Ok(())
+            }
+            Err(_) => Err(GraphError::DbNotClosed),
+        }
+    }
+
+    pub fn destroy_on_disk(self) -> Result<(), GraphError> {
+        let path = self.path.clone();
+        self.close()?;
+        TransactionDB::<MultiThreaded>::destroy(&Options::default(), path)
+            .map_err(rocks(Operation::DestroyDb))
+    }
+
+    /// Compacts `family_name`, dropping the tombstones left behind by deletes.
+    ///
+    /// This takes the graph rather than `&self`: the rocksdb 0.20
+    /// `TransactionDB` has no `compact_range_cf`, so the graph is closed and
+    /// the family compacted through a plain `DB` opened with the options
+    /// stored in `__meta__`. Reopen the graph afterwards.
+    pub fn compact_family(self, family_name: &str) -> Result<(), GraphError> {
+        let (path, family_options, config) = (self.path.clone(), self.family_options, self.config);
+        self.close()?;
+        compact_families(&path, family_options, &config, Some(family_name))
+    }
+
+    /// Compacts every family. Like `compact_family`, it closes the graph.
+    pub fn compact_all(self) -> Result<(), GraphError> {
+        let (path, family_options, config) = (self.path.clone(), self.family_options, self.config);
+        self.close()?;
+        compact_families(&path, family_options, &config, None)
+    }
+
+    /// Drops every family except `default`. RocksDB drops families one at a
+    /// time, so if one fails the error lists the families already gone.
+    pub fn destroy_everything(&self) -> Result<(), GraphError> {
+        let families: Vec<String> = self
+            .families_on_disk()?
+            .into_iter()
+            .filter(|family_name| family_name != "default")
+            .collect();
+
+        let mut dropped = Vec::new();
+        for family_name in families {
+            if let Err(source) = self.db.drop_cf(&family_name) {
+                self.clear_node_cache();
+                return Err(GraphError::DestroyIncomplete {
+                    dropped,
+                    failed: family_name,
+                    source,
+                });
+            }
+            dropped.push(family_name);
+        }
+        self.clear_node_cache();
+
+        Ok(())
+    }
+
+    /// Deletes every record in a family but keeps the family itself. Only the
+    /// family is touched: truncating a node family leaves index entries and
+    /// edges that point at the deleted nodes.
+    pub fn truncate_family(&self, family_name: &str) -> Result<usize, GraphError> {
+        let db = Arc::clone(&self.db);
+        let family = db
+            .cf_handle(family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+
+        // TransactionDB has no delete_range_cf, so keys are deleted in batches.
         let mut count = 0;
+        let mut txn = db.transaction();
+        for record in db.iterator_cf(&family, rocksdb::IteratorMode::Start) {
+            let (key, _) = record.map_err(rocks(Operation::ReadNode))?;
+            txn.delete_cf(&family, key)
+                .map_err(rocks(Operation::Delete))?;
+            count += 1;
+
+            if count % WRITE_BATCH_SIZE == 0 {
+                txn.commit().map_err(rocks(Operation::Commit))?;
+                txn = db.transaction();
+            }
+        }
+        txn.commit().map_err(rocks(Operation::Commit))?;
+        self.clear_node_cache();
+
+        Ok(count)
+    }
+
+    /// Returns up to `limit` keys of a family with their stored bytes, without
+    /// decoding them. Meant for diagnosing records that no longer match their
+    /// struct; see `preview_raw`.
+    pub fn scan_raw(
+        &self,
+        family_name: &str,
+        limit: usize,
+    ) -> Result<Vec<(String, Vec<u8>)>, GraphError> {
+        let family = self
+            .db
+            .cf_handle(family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+
+        let mut records = Vec::new();
+        for record in self
+            .db
+            .iterator_cf(&family, rocksdb::IteratorMode::Start)
+            .take(limit)
+        {
+            let (key, value) = record.map_err(rocks(Operation::ReadNode))?;
+            records.push((String::from_utf8_lossy(&key).into_owned(), value.to_vec()));
+        }
+
+        Ok(records)
+    }
+
+    /// Writes the first five records of every family to `out`, decoding
+    /// their values as `T`.
+    pub fn display_family_head<T>(&self, out: &mut impl Write) -> Result<(), GraphError>
+    where
+        T: Node,
+    {
+        let node_families =
+            DB::list_cf(&Options::default(), &self.path).map_err(rocks(Operation::FindFamilies))?;
+        for node_family_name in node_families {
+            let node_family = self
+                .db
+                .cf_handle(&node_family_name)
+                .ok_or(GraphError::NodeFamilyError)?;
+
+            let records = self
+                .db
+                .iterator_cf(&node_family, rocksdb::IteratorMode::Start);
+
+            writeln!(out, "Node family: {}", node_family_name)?;
+
+            for record in records.take(5) {
+                match record {
+                    Ok((key, value)) => {
+                        let key_str =
+                            String::from_utf8(key.to_vec()).map_err(GraphError::ParseUtf8Error)?;
+                        let value_str: T = self.codec.decode(&value)?;
+                        writeln!(out, "{}: {:?}", key_str, value_str)?;
+                    }
+                    Err(_) => return Err(GraphError::FindKeyError),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn count_nodes(&self) -> Result<usize, GraphError> {
+        let mut count = 0;
+
+        for family_name in node_families() {
+            let family = self
+                .db
+                .cf_handle(family_name)
+                .ok_or(GraphError::NodeFamilyError)?;
+
+            let records = self.db.iterator_cf(&family, rocksdb::IteratorMode::Start);
+            for record in records {
+                match record {
+                    Ok(_) => count += 1,
+                    Err(_) => return Err(GraphError::FindKeyError),
+                }
+            }
+        }
+
+        Ok(count)
+    }
+
+    pub fn count_nodes_in_family(&self, family_name: &str) -> Result<usize, GraphError> {
+        let family = self
+            .db
+            .cf_handle(family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+
+        let mut count = 0;
+        for record in self.db.iterator_cf(&family, rocksdb::IteratorMode::Start) {
+            record.map_err(rocks(Operation::ReadNode))?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    pub fn count_edges(&self) -> Result<usize, GraphError> {
+        let mut count = 0;
+
+        for family_name in edge_families() {
+            let family = self
+                .db
+                .cf_handle(family_name)
+                .ok_or(GraphError::EdgeFamilyError)?;
+
+            let records = self.db.iterator_cf(&family, rocksdb::IteratorMode::Start);
+            for record in records {
+                match record {
+                    Ok(_) => count += 1,
+                    Err(_) => return Err(GraphError::FindKeyError),
+                }
+            }
+        }
+
+        Ok(count)
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn latency_report(&self) -> LatencyReport {
+        let latencies = self.latencies.lock().unwrap();
+        LatencyReport {
+            operations: latencies
+                .iter()
+                .map(|(operation, histogram)| (operation.to_string(), histogram.summary()))
+                .collect(),
+        }
+    }
+
+    pub fn stats(&self) -> Result<GraphStats, GraphError> {
+        let mut stats = GraphStats::default();
+        for (family_names, total) in [
+            (node_families(), &mut stats.node_count),
+            (edge_families(), &mut stats.edge_count),
+        ] {
+            for family_name in family_names {
+                if self.db.cf_handle(family_name).is_none() {
+                    continue;
+                }
+                let count = self.count_nodes_in_family(family_name)?;
+                *total += count;
+                stats.family_counts.insert(family_name.to_string(), count);
+            }
+        }
+        stats.family_count = self
+            .families_on_disk()?
+            .iter()
+            .filter(|family_name| *family_name != "default")
+            .count();
+
+        // rocksdb 0.20's TransactionDB has no property getters, so
+        // rocksdb.total-sst-files-size is approximated from the files on disk.
+        for entry in std::fs::read_dir(&self.path)? {
+            let entry = entry?;
+            if entry
+                .path()
+                .extension()
+                .is_some_and(|extension| extension == "sst")
+            {
+                stats.estimated_size_bytes += entry.metadata()?.len();
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Reports what the shared block cache holds. This is a lower bound on the
+    /// graph's memory: memtables and table readers can't be measured through
+    /// `TransactionDB`, see `MemoryUsage`.
+    pub fn memory_usage(&self) -> Result<MemoryUsage, GraphError> {
+        Ok(MemoryUsage {
+            block_cache_usage: self.block_cache.get_usage(),
+            block_cache_pinned_usage: self.block_cache.get_pinned_usage(),
+        })
+    }
+
+    /// Whether the graph has no nodes. Like `count_nodes` it only looks at
+    /// node families, since an edge can't outlive its endpoints.
+    pub fn is_empty(&self) -> Result<bool, GraphError> {
+        for family_name in node_families() {
+            let family = match self.db.cf_handle(family_name) {
+                Some(family) => family,
+                None => continue,
+            };
+
+            let mut records = self.db.iterator_cf(&family, rocksdb::IteratorMode::Start);
+            if let Some(record) = records.next() {
+                record.map_err(rocks(Operation::ReadNode))?;
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    pub fn export_subgraph(
+        &self,
+        seed_ids: &[String],
+        radius: usize,
+        dest_path: &str,
+    ) -> Result<(), GraphError> {
+        let mut visited: HashSet<String> = HashSet::new();
+        let mut queue: VecDeque<(String, usize)> = VecDeque::new();
+
+        for seed_id in seed_ids {
+            if self.get_value(seed_id)?.is_none() {
+                return Err(GraphError::FindKeyError);
+            }
+            if visited.insert(seed_id.clone()) {
+                queue.push_back((seed_id.clone(), 0));
+            }
+        }
+
+        while let Some((node_id, depth)) = queue.pop_front() {
+            if depth >= radius {
+                continue;
+            }
+            let node = match self.get_value(&node_id)? {
+                Some(node) => node,
+                None => continue,
+            };
+            for edge_id in edge_ids_of(&node) {
+                let edge = match self.get_value(&edge_id)? {
+                    Some(edge) => edge,
+                    None => continue,
+                };
+                if let Some((from_id, to_id)) = endpoints_of(&edge) {
+                    for next_id in [from_id, to_id] {
+                        if visited.insert(next_id.clone()) {
+                            queue.push_back((next_id, depth + 1));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        let mut kept_edge_ids = HashSet::new();
+
+        for node_id in &visited {
+            let node = match self.get_value(node_id)? {
+                Some(node) => node,
+                None => continue,
+            };
+            for edge_id in edge_ids_of(&node) {
+                if kept_edge_ids.contains(&edge_id) {
+                    continue;
+                }
+                let edge = match self.get_value(&edge_id)? {
+                    Some(edge) => edge,
+                    None => continue,
+                };
+                if let Some((from_id, to_id)) = endpoints_of(&edge) {
+                    if visited.contains(&from_id) && visited.contains(&to_id) {
+                        kept_edge_ids.insert(edge_id.clone());
+                        edges.push((edge_id, edge));
+                    }
+                }
+            }
+            nodes.push((node_id.clone(), node));
+        }
+
+        let dest = Graph::with_codec(dest_path, self.codec)?;
+        let dest_db = Arc::clone(&dest.db);
+        let txn = dest_db.transaction();
+
+        for (node_id, mut node) in nodes {
+            for field in ["in_edge_ids", "out_edge_ids"] {
+                if let Some(Value::Array(edge_ids)) = node.get_mut(field) {
+                    edge_ids.retain(|edge_id| {
+                        edge_id_of(edge_id).is_some_and(|id| kept_edge_ids.contains(&id))
+                    });
+                }
+            }
+            let family_name = family_of(&node_id)?;
+            dest.create_family_if_not_exists(family_name)?;
+            let node_family = dest_db
+                .cf_handle(family_name)
+                .ok_or(GraphError::FindFamilyError)?;
+            if !indexed_fields(family_name).is_empty() {
+                let stored = dest.get_value_in_txn(&txn, &node_id)?;
+                dest.update_indexes(&txn, &node_id, stored.as_ref(), Some(&node))?;
+            }
+            txn.put_cf(&node_family, &node_id, dest.codec.encode(&node)?)
+                .map_err(rocks(Operation::CreateNode))?;
+        }
+
+        for (edge_id, edge) in edges {
+            let family_name = family_of(&edge_id)?;
+            dest.create_family_if_not_exists(family_name)?;
+            let edge_family = dest_db
+                .cf_handle(family_name)
+                .ok_or(GraphError::EdgeFamilyError)?;
+            txn.put_cf(&edge_family, &edge_id, dest.codec.encode(&edge)?)
+                .map_err(rocks(Operation::CreateEdge))?;
+        }
+
+        txn.commit().map_err(rocks(Operation::Commit))?;
+        Ok(())
+    }
+
+    pub fn export_family_jsonl<W: Write>(
+        &self,
+        family_name: &str,
+        writer: &mut W,
+    ) -> Result<usize, GraphError> {
+        let db = Arc::clone(&self.db);
+        let family = db
+            .cf_handle(family_name)
+            .ok_or(GraphError::FindFamilyError)?;
+
+        let mut count = 0;
+        for record in db.iterator_cf(&family, rocksdb::IteratorMode::Start) {
+            let (_, value) = record.map_err(rocks(Operation::ReadNode))?;
+            let value = self.codec.decode::<Value>(&value)?;
+            serde_json::to_writer(&mut *writer, &value)?;
+            writer.write_all(b"\n")?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    pub fn validate_node_json(&self, family: &str, value: &Value) -> Result<(), GraphError> {
+        let schema = serde_json::from_str::<Value>(schema_json())?;
+        let node_schema = schema["nodes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|node| node["name"] == family)
+            .ok_or_else(|| GraphError::MissingFamilyError(family.to_string()))?;
+        let record = value
+            .as_object()
+            .ok_or_else(|| GraphError::InvalidNodeError("expected an object".to_string()))?;
+
+        let mut expected = vec![
+            ("id", "String"),
+            ("in_edge_ids", "Vec<_>"),
+            ("out_edge_ids", "Vec<_>"),
+        ];
+        for field in node_schema["fields"].as_array().into_iter().flatten() {
+            if let (Some(name), Some(type_name)) = (field["name"].as_str(), field["type"].as_str())
+            {
+                expected.push((name, type_name));
+            }
+        }
+        if node_schema["timestamps"] == true {
+            expected.push(("created_at", "i64"));
+            expected.push(("updated_at", "i64"));
+        }
+
+        for (name, type_name) in &expected {
+            let field_value = record
+                .get(*name)
+                .ok_or_else(|| GraphError::InvalidNodeError(format!("missing field {}", name)))?;
+            if !json_matches_type(field_value, type_name) {
+                return Err(GraphError::InvalidNodeError(format!(
+                    "field {} is not a {}",
+                    name, type_name
+                )));
+            }
+        }
+        if let Some(name) = record
+            .keys()
+            .find(|key| !expected.iter().any(|(name, _)| name == key))
+        {
+            return Err(GraphError::UnknownFieldError(name.clone()));
+        }
+
+        let id = record["id"].as_str().unwrap_or_default();
+        if family_of(id)? != family {
+            return Err(GraphError::FamilyMismatch {
+                expected: family.to_string(),
+                found: family_of(id)?.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn to_dot(&self) -> Result<String, GraphError> {
+        let db = Arc::clone(&self.db);
+        let schema = serde_json::from_str::<Value>(schema_json())?;
+        let mut dot = String::from("digraph {\n");
+
+        for node_schema in schema["nodes"].as_array().into_iter().flatten() {
+            let family_name = node_schema["name"].as_str().unwrap_or_default();
+            let label_field = node_schema["fields"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .find(|field| field["type"] == "String")
+                .and_then(|field| field["name"].as_str());
+            let family = db
+                .cf_handle(family_name)
+                .ok_or(GraphError::NodeFamilyError)?;
+
+            for record in db.iterator_cf(&family, rocksdb::IteratorMode::Start) {
+                let (_, value) = record.map_err(rocks(Operation::ReadNode))?;
+                let node = self.codec.decode::<Value>(&value)?;
+                let node_id = node["id"].as_str().unwrap_or_default();
+                let label = label_field
+                    .and_then(|field| node[field].as_str())
+                    .unwrap_or(node_id);
+                dot.push_str(&format!(
+                    "    \"{}\" [label=\"{}\"];\n",
+                    dot_escape(node_id),
+                    dot_escape(label)
+                ));
+            }
+        }
+
+        for family_name in edge_families() {
+            let family = db
+                .cf_handle(family_name)
+                .ok_or(GraphError::EdgeFamilyError)?;
+
+            for record in db.iterator_cf(&family, rocksdb::IteratorMode::Start) {
+                let (_, value) = record.map_err(rocks(Operation::ReadNode))?;
+                let edge = self.codec.decode::<Value>(&value)?;
+                let (from_id, to_id) = match endpoints_of(&edge) {
+                    Some(endpoints) => endpoints,
+                    None => continue,
+                };
+                let label = edge["connection"]
+                    .as_object()
+                    .and_then(|connection| connection.keys().next())
+                    .map_or(family_name, String::as_str);
+                dot.push_str(&format!(
+                    "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                    dot_escape(&from_id),
+                    dot_escape(&to_id),
+                    dot_escape(label)
+                ));
+            }
+        }
+
+        dot.push_str("}\n");
+        Ok(dot)
+    }
+
+    pub fn export_csv<T>(&self, out: &mut impl Write) -> Result<(), GraphError>
+    where
+        T: Node,
+    {
+        let db = Arc::clone(&self.db);
+        let node_family = db.cf_handle(T::FAMILY).ok_or(GraphError::FindFamilyError)?;
+
+        let mut columns = vec!["id"];
+        columns.extend_from_slice(T::FIELDS);
+        columns.extend_from_slice(&["in_edge_ids", "out_edge_ids"]);
+        writeln!(out, "{}", columns.join(","))?;
+
+        for record in db.iterator_cf(&node_family, rocksdb::IteratorMode::Start) {
+            let (_, value) = record.map_err(rocks(Operation::ReadNode))?;
+            let node_value = node_to_value(&self.codec.decode::<T>(&value)?)?;
+            let row: Vec<String> = columns
+                .iter()
+                .map(|column| csv_cell(node_value.get(column).unwrap_or(&Value::Null)))
+                .collect();
+            writeln!(out, "{}", row.join(","))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn import_family_jsonl<R: BufRead>(
+        &self,
+        family_name: &str,
+        reader: R,
+    ) -> Result<usize, GraphError> {
+        let _timer = self.time("import_family_jsonl");
+        let values = reader.lines().filter_map(|line| match line {
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => Some(serde_json::from_str::<Value>(&line).map_err(GraphError::from)),
+            Err(error) => Some(Err(GraphError::from(error))),
+        });
+        self.import_family_values(family_name, values)
+    }
+
+    pub fn export_json<W: Write>(&self, mut writer: W) -> Result<(), GraphError> {
+        let db = Arc::clone(&self.db);
+        let families = self.families_on_disk()?;
+
+        writer.write_all(b"{")?;
+        let mut first_family = true;
+        for family_name in families {
+            if family_name == "default" || is_internal_family(&family_name) {
+                continue;
+            }
+            let family = db
+                .cf_handle(&family_name)
+                .ok_or(GraphError::FindFamilyError)?;
+
+            if !first_family {
+                writer.write_all(b",")?;
+            }
+            first_family = false;
+            serde_json::to_writer(&mut writer, &family_name)?;
+            writer.write_all(b":[")?;
+
+            let mut first_record = true;
+            for record in db.iterator_cf(&family, rocksdb::IteratorMode::Start) {
+                let (_, value) = record.map_err(rocks(Operation::ReadNode))?;
+                let value = self.codec.decode::<Value>(&value)?;
+                if !first_record {
+                    writer.write_all(b",")?;
+                }
+                first_record = false;
+                serde_json::to_writer(&mut writer, &value)?;
+            }
+            writer.write_all(b"]")?;
+        }
+        writer.write_all(b"}")?;
+
+        Ok(())
+    }
+
+    pub fn import_json<R: Read>(&self, reader: R) -> Result<(), GraphError> {
+        let document = serde_json::from_reader::<_, BTreeMap<String, Vec<Value>>>(reader)?;
+
+        for (family_name, records) in document {
+            self.import_family_values(&family_name, records.into_iter().map(Ok))?;
+        }
+
+        Ok(())
+    }
+
+    /// Copies the graph to `dest_path` and opens the copy with this graph's
+    /// codec and config. `__meta__` is copied first and every family is
+    /// created with the options stored for it, so the copy keeps them.
+    pub fn clone_to(&self, dest_path: &str) -> Result<Graph, GraphError> {
+        let db = Arc::clone(&self.db);
+        let family_names: Vec<String> = self
+            .families_on_disk()?
+            .into_iter()
+            .filter(|family_name| {
+                family_name != "default"
+                    && (!is_internal_family(family_name) || is_index_family(family_name))
+            })
+            .collect();
+        self.seed_clone(dest_path, &family_names)?;
+        let mut builder =
+            Graph::builder(dest_path).case_insensitive_families(self.case_insensitive_families);
+        builder.family_options = self.family_options;
+        builder.config = self.config;
+        builder.codec = self.codec;
+        let clone = builder.open()?;
+
+        let snapshot = db.snapshot();
+        for family_name in family_names {
+            let family = db
+                .cf_handle(&family_name)
+                .ok_or(GraphError::FindFamilyError)?;
+            let clone_family = clone.family_handle_or_create(&family_name)?;
+
+            let mut count = 0;
+            let mut txn = clone.db.transaction();
+            for record in snapshot.iterator_cf(&family, rocksdb::IteratorMode::Start) {
+                let (key, value) = record.map_err(rocks(Operation::ReadNode))?;
+                txn.put_cf(&clone_family, key, value)
+                    .map_err(rocks(Operation::CreateNode))?;
+                count += 1;
+
+                if count % WRITE_BATCH_SIZE == 0 {
+                    txn.commit().map_err(rocks(Operation::Commit))?;
+                    txn = clone.db.transaction();
+                }
+            }
+            txn.commit().map_err(rocks(Operation::Commit))?;
+        }
+
+        Ok(clone)
+    }
+
+    /// Creates the database at `dest_path` with `__meta__` and `family_names`,
+    /// each family with the options stored for it, then copies the `__meta__`
+    /// entries over.
+    fn seed_clone(&self, dest_path: &str, family_names: &[String]) -> Result<(), GraphError> {
+        let mut cf_descriptors = vec![ColumnFamilyDescriptor::new(
+            META_FAMILY,
+            self.family_options
+                .recommended_for(META_FAMILY)
+                .to_options(&self.block_cache, &self.config),
+        )];
+        for family_name in family_names {
+            let family_options = self
+                .family_options(family_name)?
+                .unwrap_or_else(|| self.family_options.recommended_for(family_name));
+            cf_descriptors.push(ColumnFamilyDescriptor::new(
+                family_name,
+                family_options.to_options(&self.block_cache, &self.config),
+            ));
+        }
+
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        let dest = DB::open_cf_descriptors(&options, dest_path, cf_descriptors)
+            .map_err(rocks(Operation::OpenDb))?;
+        let dest_meta = dest
+            .cf_handle(META_FAMILY)
+            .ok_or(GraphError::FindFamilyError)?;
+        let meta_family = self
+            .db
+            .cf_handle(META_FAMILY)
+            .ok_or(GraphError::FindFamilyError)?;
+        for record in self
+            .db
+            .iterator_cf(&meta_family, rocksdb::IteratorMode::Start)
+        {
+            let (key, value) = record.map_err(rocks(Operation::ReadNode))?;
+            dest.put_cf(dest_meta, key, value)
+                .map_err(rocks(Operation::CreateNode))?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds a RocksDB backup of the graph to `backup_dir` while writes carry
+    /// on. `BackupEngine` only accepts a plain `DB`, so the graph is first
+    /// copied through `clone_to` into a staging directory beside
+    /// `backup_dir`, which is backed up and then destroyed.
+    pub fn backup_to(&self, backup_dir: &str) -> Result<(), GraphError> {
+        let staging_path = format!("{}.staging", backup_dir.trim_end_matches('/'));
+        DB::destroy(&Options::default(), &staging_path).map_err(rocks(Operation::DestroyDb))?;
+        self.clone_to(&staging_path)?.close()?;
+
+        let backed_up =
+            open_plain(&staging_path, self.family_options, &self.config).and_then(|(db, _)| {
+                open_backup_engine(backup_dir)?
+                    .create_new_backup(&db)
+                    .map_err(GraphError::BackupError)
+            });
+        DB::destroy(&Options::default(), &staging_path).map_err(rocks(Operation::DestroyDb))?;
+        backed_up
+    }
+
+    /// Restores the latest backup in `backup_dir` to `db_path` and opens it
+    /// with the codec recorded in its `__meta__`. A backup from before the
+    /// codec was recorded opens as msgpack.
+    pub fn restore_from(backup_dir: &str, db_path: &str) -> Result<Graph, GraphError> {
+        open_backup_engine(backup_dir)?
+            .restore_from_latest_backup(db_path, db_path, &RestoreOptions::default())
+            .map_err(GraphError::BackupError)?;
+        let codec = read_storage_format(&Options::default(), db_path)?.unwrap_or_default();
+        Graph::with_codec(db_path, codec)
+    }
+
+    #[cfg(feature = "archive")]
+    pub fn export_archive(&self, out_path: &str) -> Result<(), GraphError> {
+        let families =
+            DB::list_cf(&Options::default(), &self.path).map_err(rocks(Operation::FindFamilies))?;
+        let mut archive = tar::Builder::new(std::fs::File::create(out_path)?);
+
+        for family_name in families {
+            if family_name == "default" || is_internal_family(&family_name) {
+                continue;
+            }
+            let mut entry = Vec::new();
+            self.export_family_jsonl(&family_name, &mut entry)?;
+
+            let mut header = tar::Header::new_gnu();
+            header.set_size(entry.len() as u64);
+            header.set_mode(0o644);
+            archive.append_data(&mut header, format!("{}.jsonl", family_name), &entry[..])?;
+        }
+
+        archive.finish()?;
+        Ok(())
+    }
+
+    #[cfg(feature = "archive")]
+    pub fn import_archive(&self, archive_path: &str) -> Result<(), GraphError> {
+        let mut archive = tar::Archive::new(std::fs::File::open(archive_path)?);
+
+        for entry in archive.entries()? {
+            let entry = entry?;
+            let family_name = entry
+                .path()?
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .map(String::from)
+                .ok_or(GraphError::FindFamilyError)?;
+            self.import_family_jsonl(&family_name, std::io::BufReader::new(entry))?;
+        }
+
+        Ok(())
+    }
+
+    fn resolve_id<'b>(&self, id: &'b str) -> Cow<'b, str> {
+        if !self.case_insensitive_families {
+            return Cow::Borrowed(id);
+        }
+
+        let (family_name, rest) = match id.split_once(':') {
+            Some(parts) => parts,
+            None => return Cow::Borrowed(id),
+        };
+        if self.db.cf_handle(family_name).is_some() {
+            return Cow::Borrowed(id);
+        }
 
-        for family_name in families {
-            let family = self
-                .db
-                .cf_handle(&family_name)
-                .ok_or(GraphError::NodeFamilyError)?;
+        match families()
+            .into_iter()
+            .find(|family| family.eq_ignore_ascii_case(family_name))
+        {
+            Some(family) => Cow::Owned(format!("{}:{}", family, rest)),
+            None => Cow::Borrowed(id),
+        }
+    }
 
-            let records = self.db.iterator_cf(&family, rocksdb::IteratorMode::Start);
-            for record in records {
-                match record {
-                    Ok(_) => count += 1,
-                    Err(_) => return Err(GraphError::FindKeyError),
+    fn linked_ids(
+        &self,
+        snapshot: Option<&DbSnapshot>,
+        node: &Value,
+        node_id: &str,
+        edge_family: &str,
+    ) -> Result<Vec<String>, GraphError> {
+        let mut linked = Vec::new();
+        for edge_id in edge_ids_of(node) {
+            if family_of(&edge_id)? != edge_family {
+                continue;
+            }
+            let edge = match self.get_value_in_snapshot(snapshot, &edge_id)? {
+                Some(edge) => edge,
+                None => continue,
+            };
+            match endpoints_of(&edge) {
+                Some((from_id, to_id)) if from_id == node_id => linked.push(to_id),
+                Some((from_id, _)) => linked.push(from_id),
+                None => continue,
+            }
+        }
+
+        Ok(linked)
+    }
+
+    fn undirected_adjacency<E, T>(&self) -> Result<BTreeMap<String, HashSet<String>>, GraphError>
+    where
+        E: Edge,
+        T: Node,
+    {
+        let db = Arc::clone(&self.db);
+        let node_family = db.cf_handle(T::FAMILY).ok_or(GraphError::FindFamilyError)?;
+        let edge_family = db.cf_handle(E::FAMILY).ok_or(GraphError::EdgeFamilyError)?;
+
+        let mut adjacency: BTreeMap<String, HashSet<String>> = BTreeMap::new();
+        for record in db.iterator_cf(&node_family, rocksdb::IteratorMode::Start) {
+            let (key, _) = record.map_err(rocks(Operation::ReadNode))?;
+            adjacency.insert(String::from_utf8(key.to_vec())?, HashSet::new());
+        }
+
+        for record in db.iterator_cf(&edge_family, rocksdb::IteratorMode::Start) {
+            let (_, value) = record.map_err(rocks(Operation::ReadNode))?;
+            let edge = self.codec.decode::<Value>(&value)?;
+            let (from_id, to_id) = match endpoints_of(&edge) {
+                Some(endpoints) => endpoints,
+                None => continue,
+            };
+            if from_id == to_id
+                || !adjacency.contains_key(&from_id)
+                || !adjacency.contains_key(&to_id)
+            {
+                continue;
+            }
+            if let Some(neighbours) = adjacency.get_mut(&from_id) {
+                neighbours.insert(to_id.clone());
+            }
+            if let Some(neighbours) = adjacency.get_mut(&to_id) {
+                neighbours.insert(from_id);
+            }
+        }
+
+        Ok(adjacency)
+    }
+
+    /// Every stored node mapped to the targets of its out-edges, across all
+    /// families. Edges with a missing endpoint are skipped.
+    fn directed_adjacency(&self) -> Result<BTreeMap<String, Vec<String>>, GraphError> {
+        let db = Arc::clone(&self.db);
+
+        let mut adjacency: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for family_name in node_families() {
+            let node_family = match db.cf_handle(family_name) {
+                Some(node_family) => node_family,
+                None => continue,
+            };
+            for record in db.iterator_cf(&node_family, rocksdb::IteratorMode::Start) {
+                let (key, _) = record.map_err(rocks(Operation::ReadNode))?;
+                adjacency.insert(String::from_utf8(key.to_vec())?, Vec::new());
+            }
+        }
+
+        for family_name in edge_families() {
+            let edge_family = match db.cf_handle(family_name) {
+                Some(edge_family) => edge_family,
+                None => continue,
+            };
+            for record in db.iterator_cf(&edge_family, rocksdb::IteratorMode::Start) {
+                let (_, value) = record.map_err(rocks(Operation::ReadNode))?;
+                let edge = self.codec.decode::<Value>(&value)?;
+                let (from_id, to_id) = match endpoints_of(&edge) {
+                    Some(endpoints) => endpoints,
+                    None => continue,
+                };
+                if !adjacency.contains_key(&from_id) || !adjacency.contains_key(&to_id) {
+                    continue;
+                }
+                if undirected_edge_families().contains(&family_name) {
+                    if let Some(targets) = adjacency.get_mut(&to_id) {
+                        targets.push(from_id.clone());
+                    }
                 }
+                if let Some(targets) = adjacency.get_mut(&from_id) {
+                    targets.push(to_id);
+                }
+            }
+        }
+
+        Ok(adjacency)
+    }
+
+    fn import_family_values(
+        &self,
+        family_name: &str,
+        values: impl IntoIterator<Item = Result<Value, GraphError>>,
+    ) -> Result<usize, GraphError> {
+        let db = Arc::clone(&self.db);
+        let family = self.family_handle_or_create(family_name)?;
+
+        let mut count = 0;
+        let mut txn = db.transaction();
+        for value in values {
+            let value = value?;
+            let key = value
+                .get("id")
+                .and_then(Value::as_str)
+                .ok_or(GraphError::ParseNodeIdError)?;
+
+            if !indexed_fields(family_name).is_empty() {
+                let stored = self.get_value_in_txn(&txn, key)?;
+                self.update_indexes(&txn, key, stored.as_ref(), Some(&value))?;
+            }
+            txn.put_cf(&family, key, self.codec.encode(&value)?)
+                .map_err(rocks(Operation::CreateNode))?;
+            count += 1;
+
+            if count % WRITE_BATCH_SIZE == 0 {
+                txn.commit().map_err(rocks(Operation::Commit))?;
+                txn = db.transaction();
             }
         }
+        txn.commit().map_err(rocks(Operation::Commit))?;
+        self.clear_node_cache();
 
         Ok(count)
     }
 
+    fn out_neighbours(&self, snapshot: Option<&DbSnapshot>, node: &Value) -> Vec<String> {
+        let node_id = node.get("id").and_then(Value::as_str).unwrap_or_default();
+        let edge_ids = node.get("out_edge_ids").and_then(Value::as_array);
+
+        let mut seen = HashSet::new();
+        let mut neighbours = Vec::new();
+        for edge_id in edge_ids.into_iter().flatten().filter_map(edge_id_of) {
+            let edge = match self.get_value_in_snapshot(snapshot, &edge_id) {
+                Ok(Some(edge)) => edge,
+                _ => continue,
+            };
+            if let Some(to_id) = far_endpoint(&edge_id, &edge, node_id) {
+                if seen.insert(to_id.clone()) {
+                    neighbours.push(to_id);
+                }
+            }
+        }
+
+        neighbours
+    }
+
+    fn live_neighbours(&self, node: &Value) -> Result<Vec<String>, GraphError> {
+        let node_id = node.get("id").and_then(Value::as_str).unwrap_or_default();
+        let edge_ids = node.get("out_edge_ids").and_then(Value::as_array);
+
+        let mut targets = Vec::new();
+        for edge_id in edge_ids.into_iter().flatten().filter_map(edge_id_of) {
+            let edge = match self.get_value(&edge_id)? {
+                Some(edge) => edge,
+                None => continue,
+            };
+            if let Some(to_id) = far_endpoint(&edge_id, &edge, node_id) {
+                targets.push(to_id);
+            }
+        }
+
+        Ok(targets)
+    }
+
+    #[cfg(feature = "metrics")]
+    fn time(&self, operation: &'static str) -> LatencyTimer<'_> {
+        LatencyTimer {
+            graph: self,
+            operation,
+            started: Instant::now(),
+        }
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    fn time(&self, _operation: &'static str) -> LatencyTimer {
+        LatencyTimer
+    }
+
+    fn cached_node(&self, node_id: &str) -> Option<Vec<u8>> {
+        let mut node_cache = self.node_cache.as_ref()?.lock().unwrap();
+        node_cache.entries.get(node_id).cloned()
+    }
+
+    fn node_cache_generation(&self) -> u64 {
+        match &self.node_cache {
+            Some(node_cache) => node_cache.lock().unwrap().generation,
+            None => 0,
+        }
+    }
+
+    /// Caches a value read at `generation`. If anything was invalidated since,
+    /// the read may predate a write that has already uncached the node, so
+    /// the value is dropped rather than cached stale.
+    fn cache_node(&self, node_id: &str, value: &[u8], generation: u64) {
+        if let Some(node_cache) = &self.node_cache {
+            let mut node_cache = node_cache.lock().unwrap();
+            if node_cache.generation == generation {
+                node_cache.entries.put(node_id.to_string(), value.to_vec());
+            }
+        }
+    }
+
+    fn uncache_node(&self, node_id: &str) {
+        if let Some(node_cache) = &self.node_cache {
+            let mut node_cache = node_cache.lock().unwrap();
+            node_cache.generation += 1;
+            node_cache.entries.pop(node_id);
+        }
+    }
+
+    fn clear_node_cache(&self) {
+        if let Some(node_cache) = &self.node_cache {
+            let mut node_cache = node_cache.lock().unwrap();
+            node_cache.generation += 1;
+            node_cache.entries.clear();
+        }
+    }
+
+    fn get_value(&self, id: &str) -> Result<Option<Value>, GraphError> {
+        self.get_value_in_snapshot(None, id)
+    }
+
+    fn get_value_in_snapshot(
+        &self,
+        snapshot: Option<&DbSnapshot>,
+        id: &str,
+    ) -> Result<Option<Value>, GraphError> {
+        let id = &*self.resolve_id(id);
+        let family_name = family_of(id)?;
+        let family = match self.db.cf_handle(family_name) {
+            Some(family) => family,
+            None => return Ok(None),
+        };
+        let value = match snapshot {
+            Some(snapshot) => snapshot.get_cf(&family, id),
+            None => self.db.get_cf(&family, id),
+        }
+        .map_err(rocks(Operation::ReadNode))?;
+
+        match value {
+            Some(value) => Ok(Some(self.codec.decode::<Value>(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_value_in_txn(
+        &self,
+        txn: &Transaction<TransactionDB<MultiThreaded>>,
+        id: &str,
+    ) -> Result<Option<Value>, GraphError> {
+        let id = &*self.resolve_id(id);
+        let family = self
+            .db
+            .cf_handle(family_of(id)?)
+            .ok_or(GraphError::FindFamilyError)?;
+        let value = txn
+            .get_cf(&family, id)
+            .map_err(rocks(Operation::ReadNode))?;
+
+        match value {
+            Some(value) => Ok(Some(self.codec.decode::<Value>(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn put_value_in_txn(
+        &self,
+        txn: &Transaction<TransactionDB<MultiThreaded>>,
+        id: &str,
+        value: &Value,
+    ) -> Result<(), GraphError> {
+        let family = self
+            .db
+            .cf_handle(family_of(id)?)
+            .ok_or(GraphError::FindFamilyError)?;
+        txn.put_cf(&family, id, self.codec.encode(value)?)
+            .map_err(rocks(Operation::UpdateNode))
+    }
+
+    fn reindex_node<T: Node>(
+        &self,
+        txn: &Transaction<TransactionDB<MultiThreaded>>,
+        node: &T,
+    ) -> Result<(), GraphError> {
+        if indexed_fields(T::FAMILY).is_empty() {
+            return Ok(());
+        }
+        let node_id = node.id().to_string();
+        let stored = self.get_value_in_txn(txn, &node_id)?;
+        self.update_indexes(txn, &node_id, stored.as_ref(), Some(&node_to_value(node)?))
+    }
+
+    fn update_indexes(
+        &self,
+        txn: &Transaction<TransactionDB<MultiThreaded>>,
+        node_id: &str,
+        old: Option<&Value>,
+        new: Option<&Value>,
+    ) -> Result<(), GraphError> {
+        let family_name = family_of(node_id)?;
+        for field in indexed_fields(family_name) {
+            let old_value = old.and_then(|node| node.get(*field));
+            let new_value = new.and_then(|node| node.get(*field));
+            if old_value == new_value {
+                continue;
+            }
+            let index_family = self
+                .db
+                .cf_handle(&index_family_name(family_name, field))
+                .ok_or(GraphError::FindFamilyError)?;
+            if let Some(old_value) = old_value {
+                txn.delete_cf(&index_family, index_key(old_value, node_id))
+                    .map_err(rocks(Operation::Delete))?;
+            }
+            if let Some(new_value) = new_value {
+                txn.put_cf(&index_family, index_key(new_value, node_id), [])
+                    .map_err(rocks(Operation::UpdateNode))?;
+            }
+        }
+        Ok(())
+    }
+
+    #[deprecated(note = "use the generated `Node::FAMILY` or `Edge::FAMILY` instead")]
     pub fn get_type_name<T>(&self) -> String {
         let type_name = std::any::type_name::<T>();
         let type_name = type_name.split("::").last().unwrap();
         type_name.to_string()
     }
 }
+
+fn node_to_value<T: Node>(node: &T) -> Result<Value, GraphError> {
+    Ok(rmp_serde::from_slice(&rmp_serde::to_vec_named(node)?)?)
+}
+
+fn value_to_node<T: Node>(value: &Value) -> Result<T, GraphError> {
+    Ok(rmp_serde::from_slice(&rmp_serde::to_vec_named(value)?)?)
+}
+
+fn json_matches_type(value: &Value, type_name: &str) -> bool {
+    let type_name = type_name.replace(' ', "");
+    if let Some(inner) = type_name
+        .strip_prefix("Option<")
+        .and_then(|rest| rest.strip_suffix('>'))
+    {
+        return value.is_null() || json_matches_type(value, inner);
+    }
+    if let Some(inner) = type_name
+        .strip_prefix("Vec<")
+        .and_then(|rest| rest.strip_suffix('>'))
+    {
+        return value
+            .as_array()
+            .is_some_and(|values| values.iter().all(|value| json_matches_type(value, inner)));
+    }
+
+    match type_name.as_str() {
+        "String" => value.is_string(),
+        "bool" => value.is_boolean(),
+        "u8" => value.as_u64().is_some_and(|value| value <= u8::MAX as u64),
+        "i8" | "i16" | "i32" | "i64" | "isize" => value.is_i64(),
+        "u16" | "u32" | "u64" | "usize" => value.is_u64(),
+        "f32" | "f64" => value.is_number(),
+        _ => true,
+    }
+}
+
+fn dot_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn index_family_name(family_name: &str, field: &str) -> String {
+    format!("{}_by_{}", family_name, field)
+}
+
+fn index_value(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn index_key(value: &Value, node_id: &str) -> Vec<u8> {
+    match value.as_f64() {
+        Some(number) => {
+            let mut key = ordered_f64_bytes(number).to_vec();
+            key.extend_from_slice(node_id.as_bytes());
+            key
+        }
+        None => format!("{}:{}", index_value(value), node_id).into_bytes(),
+    }
+}
+
+// Flips the sign bit of positive numbers and every bit of negative ones so
+// that the big-endian bytes sort in numeric order.
+fn ordered_f64_bytes(number: f64) -> [u8; 8] {
+    let bits = number.to_bits();
+    let ordered = match bits >> 63 {
+        0 => bits ^ (1 << 63),
+        _ => !bits,
+    };
+    ordered.to_be_bytes()
+}
+
+fn csv_cell(value: &Value) -> String {
+    let cell = match value {
+        Value::Null => String::new(),
+        Value::String(value) => value.clone(),
+        Value::Array(values) => values
+            .iter()
+            .map(|value| match value {
+                Value::String(value) => value.clone(),
+                value => edge_id_of(value).unwrap_or_else(|| value.to_string()),
+            })
+            .collect::<Vec<_>>()
+            .join(";"),
+        value => value.to_string(),
+    };
+
+    match cell.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", cell.replace('"', "\"\"")),
+        false => cell,
+    }
+}
+
+fn is_internal_family(family_name: &str) -> bool {
+    family_name == ADJACENCY_FAMILY || family_name == META_FAMILY || is_index_family(family_name)
+}
+
+fn is_index_family(family_name: &str) -> bool {
+    index_families().contains(&family_name)
+}
+
+fn read_family_options(
+    options: &Options,
+    path: &str,
+) -> Result<HashMap<String, FamilyOptions>, GraphError> {
+    let db = DB::open_cf_for_read_only(options, path, [META_FAMILY], false)
+        .map_err(rocks(Operation::OpenDb))?;
+    let meta_family = db
+        .cf_handle(META_FAMILY)
+        .ok_or(GraphError::FindFamilyError)?;
+
+    let mut family_options = HashMap::new();
+    for record in db.iterator_cf(meta_family, rocksdb::IteratorMode::Start) {
+        let (key, value) = record.map_err(rocks(Operation::ReadNode))?;
+        let key = String::from_utf8(key.to_vec())?;
+        if let Some(family_name) = key.strip_prefix(FAMILY_OPTIONS_PREFIX) {
+            family_options.insert(
+                family_name.to_string(),
+                rmp_serde::from_slice::<FamilyOptions>(&value)?,
+            );
+        }
+    }
+
+    Ok(family_options)
+}
+
+fn read_storage_format(options: &Options, path: &str) -> Result<Option<StorageFormat>, GraphError> {
+    let db = DB::open_cf_for_read_only(options, path, [META_FAMILY], false)
+        .map_err(rocks(Operation::OpenDb))?;
+    let meta_family = db
+        .cf_handle(META_FAMILY)
+        .ok_or(GraphError::FindFamilyError)?;
+    match db
+        .get_cf(meta_family, STORAGE_FORMAT_KEY)
+        .map_err(rocks(Operation::ReadNode))?
+    {
+        Some(stored) => Ok(Some(rmp_serde::from_slice(&stored)?)),
+        None => Ok(None),
+    }
+}
+
+fn check_family<T: Node>(family_name: &str) -> Result<(), GraphError> {
+    if family_name != T::FAMILY {
+        return Err(GraphError::FamilyMismatch {
+            expected: T::FAMILY.to_string(),
+            found: family_name.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// The family prefix of a `Family:key` id, split at the first colon so that
+/// keys passed to `new(Some(..))` may contain colons themselves. Ids without
+/// a family or without a key are rejected rather than guessed at.
+fn family_of(id: &str) -> Result<&str, GraphError> {
+    match id.split_once(':') {
+        Some((family, key)) if !family.is_empty() && !key.is_empty() => Ok(family),
+        _ => Err(GraphError::ParseNodeIdError),
+    }
+}
+
+/// Validates an externally supplied id against the family it's parsed as.
+/// The key isn't required to be an xid, since `new(Some(..))` and custom id
+/// generators store arbitrary keys.
+fn check_id_family(id: &str, family: &str) -> Result<(), GraphError> {
+    let found = family_of(id)?;
+    if found != family {
+        return Err(GraphError::FamilyMismatch {
+            expected: family.to_string(),
+            found: found.to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn edge_id_of(edge_id: &Value) -> Option<String> {
+    match edge_id {
+        Value::Object(variant) => variant.values().next()?.as_str().map(String::from),
+        Value::String(id) => Some(id.clone()),
+        _ => None,
+    }
+}
+
+/// Every edge id on the node, once each. An undirected edge is listed in
+/// both `in_edge_ids` and `out_edge_ids`.
+fn edge_ids_of(node: &Value) -> Vec<String> {
+    let mut seen = HashSet::new();
+    ["in_edge_ids", "out_edge_ids"]
+        .iter()
+        .filter_map(|field| node.get(field)?.as_array())
+        .flatten()
+        .filter_map(edge_id_of)
+        .filter(|edge_id| seen.insert(edge_id.clone()))
+        .collect()
+}
+
+/// Opens the graph at `path` as a plain `DB`. Each family gets the options
+/// stored for it in `__meta__`, as `GraphBuilder::open` does, so prefix
+/// extractors and compression match what the family was written with.
+fn open_plain(
+    path: &str,
+    family_options: FamilyOptions,
+    config: &GraphConfig,
+) -> Result<(DB, Vec<String>), GraphError> {
+    let options = Options::default();
+    let families = DB::list_cf(&options, path).map_err(rocks(Operation::FindFamilies))?;
+    let stored_family_options = match families.iter().any(|family| family == META_FAMILY) {
+        true => read_family_options(&options, path)?,
+        false => HashMap::new(),
+    };
+
+    let block_cache =
+        Cache::new_lru_cache(DEFAULT_BLOCK_CACHE_SIZE).map_err(rocks(Operation::OpenDb))?;
+    let cf_descriptors = families.iter().map(|family_name| {
+        let family_options = stored_family_options
+            .get(family_name)
+            .copied()
+            .unwrap_or_else(|| family_options.recommended_for(family_name));
+        ColumnFamilyDescriptor::new(family_name, family_options.to_options(&block_cache, config))
+    });
+    let db = DB::open_cf_descriptors(&options, path, cf_descriptors)
+        .map_err(rocks(Operation::OpenDb))?;
+    Ok((db, families))
+}
+
+fn open_backup_engine(backup_dir: &str) -> Result<BackupEngine, GraphError> {
+    let options = BackupEngineOptions::new(backup_dir).map_err(GraphError::BackupError)?;
+    let env = Env::new().map_err(GraphError::BackupError)?;
+    BackupEngine::open(&options, &env).map_err(GraphError::BackupError)
+}
+
+fn compact_families(
+    path: &str,
+    family_options: FamilyOptions,
+    config: &GraphConfig,
+    family_name: Option<&str>,
+) -> Result<(), GraphError> {
+    let (db, families) = open_plain(path, family_options, config)?;
+
+    let targets = match family_name {
+        Some(family_name) => vec![family_name],
+        None => families.iter().map(String::as_str).collect(),
+    };
+    for target in targets {
+        let family = db.cf_handle(target).ok_or(GraphError::FindFamilyError)?;
+        db.compact_range_cf(family, None::<&[u8]>, None::<&[u8]>);
+    }
+
+    Ok(())
+}
+
+fn find_root(parents: &mut [usize], mut node: usize) -> usize {
+    while parents[node] != node {
+        parents[node] = parents[parents[node]];
+        node = parents[node];
+    }
+    node
+}
+
+fn strip_edge_id(node: &mut Value, edge_id: &str) {
+    for field in ["in_edge_ids", "out_edge_ids"] {
+        if let Some(Value::Array(edge_ids)) = node.get_mut(field) {
+            edge_ids.retain(|entry| edge_id_of(entry).as_deref() != Some(edge_id));
+        }
+    }
+}
+
+fn repoint_edge(edge: &mut Value, from_id: &str, to_id: &str) {
+    let endpoints = edge
+        .get_mut("connection")
+        .and_then(Value::as_object_mut)
+        .and_then(|connection| connection.values_mut().next())
+        .and_then(Value::as_array_mut);
+
+    for endpoint in endpoints.into_iter().flatten() {
+        if endpoint.as_str() == Some(from_id) {
+            *endpoint = Value::String(to_id.to_string());
+        }
+    }
+}
+
+fn connection_variant(connection: &Value) -> Option<String> {
+    connection.as_object()?.keys().next().cloned()
+}
+
+fn endpoints_of(edge: &Value) -> Option<(String, String)> {
+    let connection = edge.get("connection")?.as_object()?.values().next()?;
+    let from_id = connection.get(0)?.as_str()?;
+    let to_id = connection.get(1)?.as_str()?;
+    Some((from_id.to_string(), to_id.to_string()))
+}
+
+/// The endpoint `edge` leads to when followed from `node_id`. Undirected
+/// edges lead back to `from` when followed from their `to` endpoint.
+fn far_endpoint(edge_id: &str, edge: &Value, node_id: &str) -> Option<String> {
+    let (from_id, to_id) = endpoints_of(edge)?;
+    let undirected = family_of(edge_id)
+        .map(|family| undirected_edge_families().contains(&family))
+        .unwrap_or(false);
+    if undirected && to_id == node_id {
+        Some(from_id)
+    } else {
+        Some(to_id)
+    }
+}
+
+/// The adjacency lists an edge of `edge_family` is recorded in on its `from`
+/// and `to` nodes. Undirected edges are recorded in both lists on each side.
+fn linked_fields(edge_family: &str) -> (&'static [&'static str], &'static [&'static str]) {
+    if undirected_edge_families().contains(&edge_family) {
+        (
+            &["out_edge_ids", "in_edge_ids"],
+            &["in_edge_ids", "out_edge_ids"],
+        )
+    } else {
+        (&["out_edge_ids"], &["in_edge_ids"])
+    }
+}
+
+struct FieldBytes<'a>(&'a str);
+
+impl<'de, 'a> DeserializeSeed<'de> for FieldBytes<'a> {
+    type Value = Option<Vec<u8>>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for FieldBytes<'a> {
+    type Value = Option<Vec<u8>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a node record with named fields")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut bytes = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == self.0 {
+                bytes = Some(map.next_value::<Vec<u8>>()?);
+                continue;
+            }
+            map.next_value::<IgnoredAny>()?;
+        }
+        Ok(bytes)
+    }
+}
+
+mod compaction_style {
+    use super::DBCompactionStyle;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S>(style: &Option<DBCompactionStyle>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let name = style.map(|style| match style {
+            DBCompactionStyle::Level => "level",
+            DBCompactionStyle::Universal => "universal",
+            DBCompactionStyle::Fifo => "fifo",
+        });
+        name.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DBCompactionStyle>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = Option::<String>::deserialize(deserializer)?;
+        Ok(name.and_then(|name| match name.as_str() {
+            "level" => Some(DBCompactionStyle::Level),
+            "universal" => Some(DBCompactionStyle::Universal),
+            "fifo" => Some(DBCompactionStyle::Fifo),
+            _ => None,
+        }))
+    }
+}
+
+#[cfg(feature = "metrics")]
+mod metrics {
+    use std::{collections::BTreeMap, time::Duration};
+
+    const BUCKETS: usize = 40;
+
+    #[derive(Clone)]
+    pub(crate) struct LatencyHistogram {
+        count: u64,
+        total_micros: u64,
+        max_micros: u64,
+        buckets: [u64; BUCKETS],
+    }
+
+    #[derive(Debug, Clone, Default)]
+    pub struct LatencyReport {
+        pub operations: BTreeMap<String, OperationLatency>,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct OperationLatency {
+        pub count: u64,
+        pub mean_micros: f64,
+        pub p50_micros: u64,
+        pub p99_micros: u64,
+        pub max_micros: u64,
+    }
+
+    impl Default for LatencyHistogram {
+        fn default() -> Self {
+            LatencyHistogram {
+                count: 0,
+                total_micros: 0,
+                max_micros: 0,
+                buckets: [0; BUCKETS],
+            }
+        }
+    }
+
+    impl LatencyHistogram {
+        pub(crate) fn record(&mut self, elapsed: Duration) {
+            let micros = elapsed.as_micros().min(u64::MAX as u128) as u64;
+            let bucket = (u64::BITS - micros.leading_zeros()) as usize;
+            self.buckets[bucket.min(BUCKETS - 1)] += 1;
+            self.count += 1;
+            self.total_micros = self.total_micros.saturating_add(micros);
+            self.max_micros = self.max_micros.max(micros);
+        }
+
+        // Buckets are powers of two, so percentiles report the bucket's upper
+        // bound, capped at the largest latency actually seen.
+        fn percentile(&self, quantile: f64) -> u64 {
+            let target = ((self.count as f64) * quantile).ceil().max(1.0) as u64;
+            let mut seen = 0;
+            for (bucket, count) in self.buckets.iter().enumerate() {
+                seen += count;
+                if seen >= target {
+                    let upper = 1u64.checked_shl(bucket as u32).unwrap_or(u64::MAX);
+                    return upper.saturating_sub(1).min(self.max_micros);
+                }
+            }
+            self.max_micros
+        }
+
+        pub(crate) fn summary(&self) -> OperationLatency {
+            OperationLatency {
+                count: self.count,
+                mean_micros: match self.count {
+                    0 => 0.0,
+                    count => self.total_micros as f64 / count as f64,
+                },
+                p50_micros: self.percentile(0.5),
+                p99_micros: self.percentile(0.99),
+                max_micros: self.max_micros,
+            }
+        }
+    }
+}
@@ -0,0 +1,39 @@
+#![cfg(feature = "archive")]
+
+mod common;
+
+use common::TestDir;
+use graph::*;
+
+#[test]
+fn archive_round_trip_restores_a_destroyed_graph() {
+    let dir = TestDir::new("archive");
+    let path = dir.path("db");
+    let archive_path = dir.path("graph.tar");
+
+    let graph = Graph::new(&path).unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    let edge = graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+            0.5,
+        ))
+        .unwrap();
+    graph.export_archive(&archive_path).unwrap();
+    graph.destroy_on_disk().unwrap();
+
+    let restored = Graph::new(&path).unwrap();
+    assert!(restored.is_empty().unwrap());
+    restored.import_archive(&archive_path).unwrap();
+    assert_eq!(restored.count_nodes().unwrap(), 2);
+    let song: Song = restored.get_node(song.id().to_string()).unwrap();
+    assert_eq!(song.title(), "Matter");
+    let edge: By = restored.get_edge(edge.id().clone()).unwrap();
+    assert_eq!(*edge.weight(), 0.5);
+}
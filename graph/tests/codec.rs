@@ -0,0 +1,130 @@
+mod common;
+
+use common::TestDir;
+use graph::*;
+
+/// Runs the same reads and writes against any codec.
+fn exercise(graph: &Graph) {
+    let song = graph
+        .add_node(Song::new(
+            None,
+            "Matter".to_string(),
+            vec!["rock".to_string()],
+        ))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    let album = graph
+        .add_node(Album::new(None, "Matter".to_string(), vec![1, 2, 3], 1999))
+        .unwrap();
+    let edge = graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+            0.5,
+        ))
+        .unwrap();
+
+    let stored: Song = graph.get_node(song.id().to_string()).unwrap();
+    assert_eq!(stored.out_edge_ids().len(), 1);
+    let stored: By = graph.get_edge(edge.id().clone()).unwrap();
+    assert_eq!(*stored.weight(), 0.5);
+    assert_eq!(graph.find_by::<Song>("title", "Matter").unwrap().len(), 1);
+    assert_eq!(
+        graph
+            .get_any_node(&artist.id().to_string())
+            .unwrap()
+            .in_degree(),
+        1
+    );
+    assert_eq!(
+        graph
+            .get_node_field_bytes(&album.id().to_string(), "artwork")
+            .unwrap(),
+        Some(vec![1, 2, 3])
+    );
+    assert_eq!(
+        graph.get_neighbors::<Song>(&song.id().to_string()).unwrap(),
+        vec![artist.id().to_string()]
+    );
+    graph
+        .remove_node_cascade::<Artist>(&artist.id().to_string())
+        .unwrap();
+    let stored: Song = graph.get_node(song.id().to_string()).unwrap();
+    assert!(stored.out_edge_ids().is_empty());
+}
+
+#[test]
+fn json_codec_behaves_like_msgpack() {
+    let dir = TestDir::new("codecs");
+    exercise(&Graph::new(&dir.path("msgpack")).unwrap());
+
+    let graph = Graph::with_codec(&dir.path("json"), JsonCodec).unwrap();
+    exercise(&graph);
+    let (_, bytes) = graph.scan_raw("Song", 1).unwrap().remove(0);
+    assert!(String::from_utf8(bytes.clone())
+        .unwrap()
+        .starts_with("{\"id\":\"Song:"));
+    assert!(preview_raw(&bytes).contains("Matter"));
+}
+
+#[test]
+fn export_subgraph_keeps_the_codec_and_indexes() {
+    let dir = TestDir::new("codec-export-subgraph");
+    let graph = Graph::with_codec(&dir.path("source"), JsonCodec).unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let album = graph
+        .add_node(Album::new(None, "Matter".to_string(), vec![], 1999))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    for connection in [
+        ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+        ByConnection::AlbumIsBy(album.id().clone(), artist.id().clone()),
+    ] {
+        graph.add_edge(By::new(None, connection, 1.0)).unwrap();
+    }
+
+    let dest_path = dir.path("subgraph");
+    graph
+        .export_subgraph(&[song.id().to_string()], 2, &dest_path)
+        .unwrap();
+    let subgraph = Graph::with_codec(&dest_path, JsonCodec).unwrap();
+    let stored: Song = subgraph.get_node(song.id().to_string()).unwrap();
+    assert_eq!(stored.title(), "Matter");
+    assert_eq!(
+        subgraph.find_by::<Song>("title", "Matter").unwrap().len(),
+        1
+    );
+    assert_eq!(
+        subgraph
+            .find_in_range::<Album>("year", 1990.0, 2000.0)
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn restore_from_reopens_with_the_recorded_codec() {
+    let dir = TestDir::new("codec-restore");
+    let graph = Graph::with_codec(&dir.path("source"), JsonCodec).unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let backup_dir = dir.path("backup");
+    graph.backup_to(&backup_dir).unwrap();
+
+    let restored = Graph::restore_from(&backup_dir, &dir.path("restored")).unwrap();
+    let stored: Song = restored.get_node(song.id().to_string()).unwrap();
+    assert_eq!(stored.title(), "Matter");
+    restored
+        .add_node(Song::new(None, "Tides".to_string(), vec![]))
+        .unwrap();
+    let (_, bytes) = restored.scan_raw("Song", 1).unwrap().remove(0);
+    assert!(bytes.starts_with(b"{"));
+}
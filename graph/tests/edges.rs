@@ -0,0 +1,621 @@
+mod common;
+
+use common::TestDir;
+use graph::*;
+use std::thread::sleep;
+use std::time::Duration;
+
+fn song_and_artist(graph: &Graph) -> (SongId, ArtistId) {
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    (song.id().clone(), artist.id().clone())
+}
+
+#[test]
+fn edges_created_between_filters_by_timestamp() {
+    let dir = TestDir::new("edges-created-between");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let (song_id, artist_id) = song_and_artist(&graph);
+
+    let mut edges = Vec::new();
+    for _ in 0..3 {
+        let edge = By::new(
+            None,
+            ByConnection::SongIsBy(song_id.clone(), artist_id.clone()),
+            1.0,
+        );
+        edges.push(graph.add_edge(edge).unwrap());
+        sleep(Duration::from_millis(5));
+    }
+    let stamps: Vec<i64> = edges
+        .iter()
+        .map(|edge| edge.created_at().unwrap())
+        .collect();
+    assert!(stamps.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let window = graph
+        .edges_created_between::<By>(stamps[1], stamps[2])
+        .unwrap();
+    let mut window_ids: Vec<String> = window.iter().map(|edge| edge.id().to_string()).collect();
+    window_ids.sort();
+    let mut expected = vec![edges[1].id().to_string(), edges[2].id().to_string()];
+    expected.sort();
+    assert_eq!(window_ids, expected);
+    assert!(graph
+        .edges_created_between::<By>(0, stamps[0] - 1)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn edge_type_counts_buckets_by_family() {
+    let dir = TestDir::new("edge-type-counts");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let (song_id, artist_id) = song_and_artist(&graph);
+    let other_artist = graph
+        .add_node(Artist::new(None, "Other".to_string(), None))
+        .unwrap();
+    let playlist = graph.add_node(Playlist::new(None)).unwrap();
+    for artist in [&artist_id, other_artist.id()] {
+        graph
+            .add_edge(By::new(
+                None,
+                ByConnection::SongIsBy(song_id.clone(), artist.clone()),
+                1.0,
+            ))
+            .unwrap();
+    }
+    graph
+        .add_edge(Contains::new(
+            None,
+            ContainsConnection::PlaylistContainsSong(playlist.id().clone(), song_id.clone()),
+        ))
+        .unwrap();
+
+    let counts = graph.edge_type_counts(&song_id.to_string()).unwrap();
+    assert_eq!(counts.len(), 2);
+    assert_eq!(counts["By"], (0, 2));
+    assert_eq!(counts["Contains"], (1, 0));
+}
+
+#[test]
+fn iter_incident_edges_spans_edge_families() {
+    let dir = TestDir::new("incident-edges");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let (song_id, artist_id) = song_and_artist(&graph);
+    let playlist = graph.add_node(Playlist::new(None)).unwrap();
+    let by = graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song_id.clone(), artist_id),
+            1.0,
+        ))
+        .unwrap();
+    let contains = graph
+        .add_edge(Contains::new(
+            None,
+            ContainsConnection::PlaylistContainsSong(playlist.id().clone(), song_id.clone()),
+        ))
+        .unwrap();
+
+    let edges: Vec<serde_json::Value> = graph
+        .iter_incident_edges(&song_id.to_string())
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let edge_ids: Vec<&str> = edges
+        .iter()
+        .map(|edge| edge["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        edge_ids,
+        vec![contains.id().to_string(), by.id().to_string()]
+    );
+    assert_eq!(edges[1]["weight"], 1.0);
+}
+
+#[test]
+fn iter_incident_edges_yields_an_undirected_edge_once() {
+    let dir = TestDir::new("incident-undirected");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let first = graph
+        .add_node_get_id(Artist::new(None, "First".to_string(), None))
+        .unwrap();
+    let second = graph
+        .add_node_get_id(Artist::new(None, "Second".to_string(), None))
+        .unwrap();
+    let collaboration = graph
+        .add_edge(CollaboratedWith::new(
+            None,
+            CollaboratedWithConnection::ArtistCollaboratedWith(first.clone(), second),
+        ))
+        .unwrap();
+
+    let edges: Vec<serde_json::Value> = graph
+        .iter_incident_edges(&first.to_string())
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(edges.len(), 1);
+    assert_eq!(edges[0]["id"], collaboration.id().to_string());
+}
+
+#[test]
+fn add_edge_links_both_endpoints() {
+    let dir = TestDir::new("add-edge");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let (song_id, artist_id) = song_and_artist(&graph);
+    let edge = graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song_id.clone(), artist_id.clone()),
+            1.0,
+        ))
+        .unwrap();
+
+    let song: Song = graph.get_node(song_id.to_string()).unwrap();
+    let artist: Artist = graph.get_node(artist_id.to_string()).unwrap();
+    assert_eq!(
+        song.out_edge_ids(),
+        vec![SongOutEdge::ById(edge.id().clone())]
+    );
+    assert_eq!(
+        artist.in_edge_ids(),
+        vec![ArtistInEdge::ById(edge.id().clone())]
+    );
+    let stored: By = graph.get_edge(edge.id().clone()).unwrap();
+    assert_eq!(stored.id(), edge.id());
+}
+
+#[test]
+fn add_edge_to_a_missing_node_writes_nothing() {
+    let dir = TestDir::new("add-edge-missing");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let (song_id, _) = song_and_artist(&graph);
+    let dangling = By::new(
+        None,
+        ByConnection::SongIsBy(song_id.clone(), Artist::new_id("missing".to_string())),
+        1.0,
+    );
+
+    assert!(matches!(
+        graph.add_edge(dangling.clone()),
+        Err(GraphError::FindKeyError)
+    ));
+    assert!(graph.get_edge::<ById, By>(dangling.id().clone()).is_err());
+    let song: Song = graph.get_node(song_id.to_string()).unwrap();
+    assert!(song.out_edge_ids().is_empty());
+}
+
+#[test]
+fn remove_edge_unlinks_both_endpoints() {
+    let dir = TestDir::new("remove-edge");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let (song_id, artist_id) = song_and_artist(&graph);
+    let edges: Vec<By> = (0..2)
+        .map(|_| {
+            graph
+                .add_edge(By::new(
+                    None,
+                    ByConnection::SongIsBy(song_id.clone(), artist_id.clone()),
+                    1.0,
+                ))
+                .unwrap()
+        })
+        .collect();
+
+    graph.remove_edge::<By>(edges[0].id().clone()).unwrap();
+    // The graph is still usable after a removal.
+    let song: Song = graph.get_node(song_id.to_string()).unwrap();
+    let artist: Artist = graph.get_node(artist_id.to_string()).unwrap();
+    assert_eq!(
+        song.out_edge_ids(),
+        vec![SongOutEdge::ById(edges[1].id().clone())]
+    );
+    assert_eq!(
+        artist.in_edge_ids(),
+        vec![ArtistInEdge::ById(edges[1].id().clone())]
+    );
+    assert!(matches!(
+        graph.get_edge::<ById, By>(edges[0].id().clone()),
+        Err(GraphError::FindKeyError)
+    ));
+
+    graph.remove_edge::<By>(edges[1].id().clone()).unwrap();
+    let song: Song = graph.get_node(song_id.to_string()).unwrap();
+    assert!(song.out_edge_ids().is_empty());
+}
+
+#[test]
+fn add_edges_updates_every_endpoint_once() {
+    let dir = TestDir::new("add-edges");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    let songs: Vec<Song> = (0..3)
+        .map(|i| Song::new(None, format!("Track {}", i), vec![]))
+        .collect();
+    graph.add_nodes(songs.clone()).unwrap();
+    let edges: Vec<By> = songs
+        .iter()
+        .map(|song| {
+            By::new(
+                None,
+                ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+                1.0,
+            )
+        })
+        .collect();
+    graph.add_edges(edges.clone()).unwrap();
+
+    assert_eq!(graph.count_edges().unwrap(), 3);
+    let artist: Artist = graph.get_node(artist.id().to_string()).unwrap();
+    assert_eq!(artist.in_edge_ids().len(), 3);
+    for (song, edge) in songs.iter().zip(&edges) {
+        let song: Song = graph.get_node(song.id().to_string()).unwrap();
+        assert_eq!(
+            song.out_edge_ids(),
+            vec![SongOutEdge::ById(edge.id().clone())]
+        );
+    }
+}
+
+#[test]
+fn add_edges_with_a_missing_endpoint_writes_nothing() {
+    let dir = TestDir::new("add-edges-missing");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let (song_id, artist_id) = song_and_artist(&graph);
+    let edges = vec![
+        By::new(
+            None,
+            ByConnection::SongIsBy(song_id.clone(), artist_id.clone()),
+            1.0,
+        ),
+        By::new(
+            None,
+            ByConnection::SongIsBy(Song::new_id("missing".to_string()), artist_id.clone()),
+            1.0,
+        ),
+    ];
+
+    assert!(graph.add_edges(edges).is_err());
+    assert_eq!(graph.count_edges().unwrap(), 0);
+    let artist: Artist = graph.get_node(artist_id.to_string()).unwrap();
+    assert!(artist.in_edge_ids().is_empty());
+}
+
+#[test]
+fn get_any_edge_reports_family_and_endpoints() {
+    let dir = TestDir::new("get-any-edge");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let (song_id, artist_id) = song_and_artist(&graph);
+    let edge = graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song_id.clone(), artist_id.clone()),
+            1.0,
+        ))
+        .unwrap();
+
+    let any = graph.get_any_edge(&edge.id().to_string()).unwrap();
+    assert!(matches!(any, AnyEdge::By(_)));
+    assert_eq!(any.family_name(), "By");
+    assert_eq!(
+        any.endpoints(),
+        (song_id.to_string(), artist_id.to_string())
+    );
+    assert!(matches!(
+        graph.get_any_edge("By:missing"),
+        Err(GraphError::FindKeyError)
+    ));
+    // A node id is not an edge.
+    assert!(matches!(
+        graph.get_any_edge(&song_id.to_string()),
+        Err(GraphError::EdgeFamilyError)
+    ));
+}
+
+#[test]
+fn edge_lookups_and_merges_resolve_case_insensitive_ids() {
+    let dir = TestDir::new("edge-lookups-case");
+    let graph = Graph::builder(&dir.path("db"))
+        .case_insensitive_families(true)
+        .open()
+        .unwrap();
+    let (song_id, artist_id) = song_and_artist(&graph);
+    let edge = graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song_id.clone(), artist_id.clone()),
+            1.0,
+        ))
+        .unwrap();
+    let duplicate = graph
+        .add_node_get_id(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+
+    let lowercase = |id: String| id.to_lowercase();
+    let any = graph
+        .get_any_edge(&lowercase(edge.id().to_string()))
+        .unwrap();
+    assert!(matches!(any, AnyEdge::By(_)));
+    assert_eq!(
+        graph.neighbours(&lowercase(song_id.to_string())).unwrap(),
+        vec![artist_id.to_string()]
+    );
+
+    graph
+        .merge_nodes(
+            &lowercase(artist_id.to_string()),
+            &[lowercase(duplicate.to_string())],
+        )
+        .unwrap();
+    assert!(!graph.node_exists(&duplicate.to_string()).unwrap());
+    assert_eq!(graph.degree(&artist_id.to_string()).unwrap(), 1);
+}
+
+#[test]
+fn connections_with_wrong_family_endpoints_are_rejected() {
+    let dir = TestDir::new("invalid-connection");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let album = graph
+        .add_node(Album::new(None, "Matter".to_string(), vec![], 2019))
+        .unwrap();
+    let (_, artist_id) = song_and_artist(&graph);
+    // Smuggle an Album id into a Song slot the way a bad payload would.
+    let not_a_song: SongId = serde_json::from_value(album.id().to_string().into()).unwrap();
+    let edge = By::new(None, ByConnection::SongIsBy(not_a_song, artist_id), 1.0);
+
+    assert!(matches!(
+        edge.connection().validate(),
+        Err(id) if id == album.id().to_string()
+    ));
+    assert!(matches!(
+        graph.add_edge(edge.clone()),
+        Err(GraphError::InvalidConnection(_))
+    ));
+    assert!(matches!(
+        graph.add_edges(vec![edge]),
+        Err(GraphError::InvalidConnection(_))
+    ));
+    assert_eq!(graph.count_edges().unwrap(), 0);
+}
+
+#[test]
+fn remove_node_cascade_deletes_incident_edges() {
+    let dir = TestDir::new("remove-node-cascade");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let playlist = graph.add_node(Playlist::new(None)).unwrap();
+    let (song_id, artist_id) = song_and_artist(&graph);
+    let contains = graph
+        .add_edge(Contains::new(
+            None,
+            ContainsConnection::PlaylistContainsSong(playlist.id().clone(), song_id.clone()),
+        ))
+        .unwrap();
+    let by = graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song_id.clone(), artist_id.clone()),
+            1.0,
+        ))
+        .unwrap();
+
+    assert!(graph
+        .remove_node_cascade::<Artist>(&song_id.to_string())
+        .is_err());
+    graph
+        .remove_node_cascade::<Song>(&song_id.to_string())
+        .unwrap();
+
+    assert!(!graph.node_exists(&song_id.to_string()).unwrap());
+    assert!(matches!(
+        graph.get_edge::<_, Contains>(contains.id().clone()),
+        Err(GraphError::FindKeyError)
+    ));
+    assert!(matches!(
+        graph.get_edge::<_, By>(by.id().clone()),
+        Err(GraphError::FindKeyError)
+    ));
+    let playlist: Playlist = graph.get_node(playlist.id().to_string()).unwrap();
+    let artist: Artist = graph.get_node(artist_id.to_string()).unwrap();
+    assert!(playlist.out_edge_ids().is_empty());
+    assert!(artist.in_edge_ids().is_empty());
+}
+
+#[test]
+fn degrees_count_edge_ids_on_the_node() {
+    let dir = TestDir::new("degrees");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let playlist = graph.add_node(Playlist::new(None)).unwrap();
+    let (song_id, artist_id) = song_and_artist(&graph);
+    graph
+        .add_edge(Contains::new(
+            None,
+            ContainsConnection::PlaylistContainsSong(playlist.id().clone(), song_id.clone()),
+        ))
+        .unwrap();
+    graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song_id.clone(), artist_id),
+            1.0,
+        ))
+        .unwrap();
+
+    let song = song_id.to_string();
+    assert_eq!(graph.in_degree(&song).unwrap(), 1);
+    assert_eq!(graph.out_degree(&song).unwrap(), 1);
+    assert_eq!(graph.degree(&song).unwrap(), 2);
+    assert_eq!(graph.degree(&playlist.id().to_string()).unwrap(), 1);
+    assert!(matches!(
+        graph.degree("Song:missing"),
+        Err(GraphError::FindKeyError)
+    ));
+}
+
+#[test]
+fn degree_counts_an_undirected_edge_once() {
+    let dir = TestDir::new("undirected-degree");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let first = graph
+        .add_node_get_id(Artist::new(None, "First".to_string(), None))
+        .unwrap();
+    let second = graph
+        .add_node_get_id(Artist::new(None, "Second".to_string(), None))
+        .unwrap();
+    graph
+        .add_edge(CollaboratedWith::new(
+            None,
+            CollaboratedWithConnection::ArtistCollaboratedWith(first.clone(), second.clone()),
+        ))
+        .unwrap();
+
+    for artist in [first.to_string(), second.to_string()] {
+        assert_eq!(graph.in_degree(&artist).unwrap(), 1);
+        assert_eq!(graph.out_degree(&artist).unwrap(), 1);
+        assert_eq!(graph.degree(&artist).unwrap(), 1);
+    }
+}
+
+#[test]
+fn out_edges_and_in_edges_return_typed_edges() {
+    let dir = TestDir::new("typed-incident-edges");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let playlist = graph.add_node(Playlist::new(None)).unwrap();
+    let (song_id, artist_id) = song_and_artist(&graph);
+    let other = graph
+        .add_node(Artist::new(None, "Other".to_string(), None))
+        .unwrap();
+    graph
+        .add_edge(Contains::new(
+            None,
+            ContainsConnection::PlaylistContainsSong(playlist.id().clone(), song_id.clone()),
+        ))
+        .unwrap();
+    for (artist_id, weight) in [(artist_id, 0.25), (other.id().clone(), 0.75)] {
+        graph
+            .add_edge(By::new(
+                None,
+                ByConnection::SongIsBy(song_id.clone(), artist_id),
+                weight,
+            ))
+            .unwrap();
+    }
+
+    let song = song_id.to_string();
+    let mut weights: Vec<f64> = graph
+        .out_edges::<Song, By>(&song)
+        .unwrap()
+        .iter()
+        .map(|edge| *edge.weight())
+        .collect();
+    weights.sort_by(f64::total_cmp);
+    assert_eq!(weights, vec![0.25, 0.75]);
+    assert!(graph.out_edges::<Song, Contains>(&song).unwrap().is_empty());
+    assert_eq!(graph.in_edges::<Song, Contains>(&song).unwrap().len(), 1);
+    assert!(graph.in_edges::<Song, By>(&song).unwrap().is_empty());
+}
+
+#[test]
+fn add_edge_returns_the_stored_edge() {
+    let dir = TestDir::new("add-edge-returns");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let (song_id, artist_id) = song_and_artist(&graph);
+    let edge = graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song_id, artist_id),
+            0.5,
+        ))
+        .unwrap();
+
+    let stored: By = graph.get_edge(edge.id().clone()).unwrap();
+    assert_eq!(stored.weight(), edge.weight());
+    assert_eq!(stored.created_at(), edge.created_at());
+}
+
+#[test]
+fn edges_between_matches_the_connection_endpoints() {
+    let dir = TestDir::new("edges-between");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let (song_id, artist_id) = song_and_artist(&graph);
+    let other_id = graph
+        .add_node(Artist::new(None, "Other".to_string(), None))
+        .unwrap()
+        .id()
+        .clone();
+    for (artist_id, weight) in [
+        (artist_id.clone(), 1.0),
+        (artist_id.clone(), 2.0),
+        (other_id.clone(), 3.0),
+    ] {
+        graph
+            .add_edge(By::new(
+                None,
+                ByConnection::SongIsBy(song_id.clone(), artist_id),
+                weight,
+            ))
+            .unwrap();
+    }
+
+    let (song, artist, other) = (
+        song_id.to_string(),
+        artist_id.to_string(),
+        other_id.to_string(),
+    );
+    let mut weights: Vec<f64> = graph
+        .edges_between::<By>(&song, &artist)
+        .unwrap()
+        .iter()
+        .map(|edge| *edge.weight())
+        .collect();
+    weights.sort_by(f64::total_cmp);
+    assert_eq!(weights, vec![1.0, 2.0]);
+    assert_eq!(graph.edges_between::<By>(&song, &other).unwrap().len(), 1);
+    assert!(graph
+        .edges_between::<By>(&artist, &song)
+        .unwrap()
+        .is_empty());
+
+    // Undirected edges match from either end.
+    graph
+        .add_edge(CollaboratedWith::new(
+            None,
+            CollaboratedWithConnection::ArtistCollaboratedWith(artist_id, other_id),
+        ))
+        .unwrap();
+    assert_eq!(
+        graph
+            .edges_between::<CollaboratedWith>(&other, &artist)
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn add_edge_unique_updates_the_existing_edge() {
+    let dir = TestDir::new("add-edge-unique");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let (song_id, artist_id) = song_and_artist(&graph);
+    let connection = || ByConnection::SongIsBy(song_id.clone(), artist_id.clone());
+
+    let first = graph
+        .add_edge_unique(By::new(None, connection(), 1.0))
+        .unwrap();
+    let second = graph
+        .add_edge_unique(By::new(None, connection(), 5.0))
+        .unwrap();
+    assert_eq!(first.id(), second.id());
+    assert_eq!(second.created_at(), first.created_at());
+    let stored: By = graph.get_edge(first.id().clone()).unwrap();
+    assert_eq!(*stored.weight(), 5.0);
+    let song: Song = graph.get_node(song_id.to_string()).unwrap();
+    assert_eq!(song.out_edge_ids().len(), 1);
+}
@@ -0,0 +1,38 @@
+use graph::*;
+use std::error::Error;
+
+fn parse_song(json: &str) -> Result<Song, GraphError> {
+    Ok(serde_json::from_str(json)?)
+}
+
+fn read_missing_file(path: &str) -> Result<String, GraphError> {
+    Ok(std::fs::read_to_string(path)?)
+}
+
+fn decode_utf8(bytes: Vec<u8>) -> Result<String, GraphError> {
+    Ok(String::from_utf8(bytes)?)
+}
+
+#[test]
+fn question_mark_converts_library_errors() {
+    let error = parse_song("not json").unwrap_err();
+    assert!(matches!(error, GraphError::JsonError(_)));
+    assert!(error.to_string().starts_with("JSON error: "));
+
+    let error = read_missing_file("/nonexistent/graphite").unwrap_err();
+    assert!(matches!(error, GraphError::IoError(_)));
+    assert!(error.source().is_some());
+
+    let error = decode_utf8(vec![0xff]).unwrap_err();
+    assert!(matches!(error, GraphError::ParseUtf8Error(_)));
+}
+
+#[test]
+fn unit_variants_have_readable_messages() {
+    assert_eq!(GraphError::FindKeyError.to_string(), "Find key error");
+    assert_eq!(
+        GraphError::MissingFieldError("title".to_string()).to_string(),
+        "Missing field: title"
+    );
+    assert!(GraphError::FindKeyError.source().is_none());
+}
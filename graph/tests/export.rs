@@ -0,0 +1,146 @@
+mod common;
+
+use common::TestDir;
+use graph::*;
+
+#[test]
+fn export_csv_quotes_and_joins_fields() {
+    let dir = TestDir::new("export-csv");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    graph
+        .add_node(Song::new(
+            Some("quoted".to_string()),
+            "a, \"b\"".to_string(),
+            vec!["rock".to_string(), "live".to_string()],
+        ))
+        .unwrap();
+    graph
+        .add_node(Song::new(
+            Some("plain".to_string()),
+            "plain".to_string(),
+            vec![],
+        ))
+        .unwrap();
+
+    let mut out = Vec::new();
+    graph.export_csv::<Song>(&mut out).unwrap();
+    let csv = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "id,title,tags,in_edge_ids,out_edge_ids");
+    assert_eq!(lines.len(), 3);
+    assert!(
+        lines.contains(&"Song:quoted,\"a, \"\"b\"\"\",rock;live,,"),
+        "{}",
+        csv
+    );
+    assert!(lines.contains(&"Song:plain,plain,,,"), "{}", csv);
+}
+
+#[test]
+fn to_dot_emits_labelled_nodes_and_edges() {
+    let dir = TestDir::new("to-dot");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let song = graph
+        .add_node(Song::new(
+            Some("s".to_string()),
+            "Say \"hi\"".to_string(),
+            vec![],
+        ))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(
+            Some("a".to_string()),
+            "Family Stereo".to_string(),
+            None,
+        ))
+        .unwrap();
+    graph
+        .add_node(Playlist::new(Some("p".to_string())))
+        .unwrap();
+    graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+            1.0,
+        ))
+        .unwrap();
+
+    let dot = graph.to_dot().unwrap();
+    assert!(dot.starts_with("digraph {\n"));
+    assert!(
+        dot.contains("    \"Song:s\" [label=\"Say \\\"hi\\\"\"];\n"),
+        "{}",
+        dot
+    );
+    // Nodes without a string field fall back to their id.
+    assert!(
+        dot.contains("    \"Playlist:p\" [label=\"Playlist:p\"];\n"),
+        "{}",
+        dot
+    );
+    assert!(
+        dot.contains("    \"Song:s\" -> \"Artist:a\" [label=\"SongIsBy\"];\n"),
+        "{}",
+        dot
+    );
+}
+
+#[test]
+fn export_json_round_trips_into_a_fresh_graph() {
+    let dir = TestDir::new("export-json");
+    let graph = Graph::new(&dir.path("source")).unwrap();
+    let song = graph
+        .add_node(Song::new(
+            None,
+            "Matter".to_string(),
+            vec!["rock".to_string()],
+        ))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    let edge = graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+            1.5,
+        ))
+        .unwrap();
+
+    let mut out = Vec::new();
+    graph.export_json(&mut out).unwrap();
+    graph.destroy_on_disk().unwrap();
+
+    let restored = Graph::new(&dir.path("restored")).unwrap();
+    restored.import_json(&out[..]).unwrap();
+    assert_eq!(restored.count_nodes().unwrap(), 2);
+    assert_eq!(restored.count_edges().unwrap(), 1);
+    let song: Song = restored.get_node(song.id().to_string()).unwrap();
+    assert_eq!(song.tags(), &vec!["rock".to_string()]);
+    assert_eq!(
+        song.out_edge_ids(),
+        vec![SongOutEdge::ById(edge.id().clone())]
+    );
+    let stored: By = restored.get_edge(edge.id().clone()).unwrap();
+    assert_eq!(*stored.weight(), 1.5);
+}
+
+#[test]
+fn export_csv_columns_follow_field_names() {
+    let dir = TestDir::new("export-csv-fields");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    graph
+        .add_node(Artist::new(Some("a".to_string()), "Ann".to_string(), None))
+        .unwrap();
+
+    let mut out = Vec::new();
+    graph.export_csv::<Artist>(&mut out).unwrap();
+    let csv = String::from_utf8(out).unwrap();
+    let rows: Vec<Vec<&str>> = csv.lines().map(|line| line.split(',').collect()).collect();
+    let mut header = vec!["id"];
+    header.extend_from_slice(Artist::field_names());
+    header.extend_from_slice(&["in_edge_ids", "out_edge_ids"]);
+    assert_eq!(rows[0], header);
+    assert_eq!(rows[1], vec!["Artist:a", "Ann", "", "", ""]);
+    assert_eq!(rows.len(), 2);
+}
@@ -0,0 +1,30 @@
+// The id generator is process-wide, so these tests live in their own binary.
+
+use graph::*;
+
+fn generate_song_ids() -> Vec<String> {
+    (0..3)
+        .map(|_| {
+            Song::new(None, "Matter".to_string(), vec![])
+                .id()
+                .to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn deterministic_ids_repeat_across_runs() {
+    use_deterministic_ids(7);
+    let first = generate_song_ids();
+    use_deterministic_ids(7);
+    let second = generate_song_ids();
+    use_deterministic_ids(8);
+    let other_seed = generate_song_ids();
+    reset_id_generator();
+
+    assert_eq!(first, second);
+    assert_ne!(first, other_seed);
+    assert_ne!(first[0], first[1]);
+    assert!(first.iter().all(|id| id.starts_with("Song:")));
+    assert_ne!(generate_song_ids(), first);
+}
@@ -0,0 +1,139 @@
+mod common;
+
+use common::TestDir;
+use graph::*;
+
+fn song(key: &str, title: &str) -> Song {
+    Song::new(Some(key.to_string()), title.to_string(), vec![])
+}
+
+fn ids_titled(graph: &Graph, title: &str) -> Vec<String> {
+    let mut ids: Vec<String> = graph
+        .find_by::<Song>("title", title)
+        .unwrap()
+        .iter()
+        .map(|song| song.id().to_string())
+        .collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn find_by_only_accepts_indexed_fields() {
+    let dir = TestDir::new("find-by-unindexed");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    graph.add_node(song("a", "Matter")).unwrap();
+
+    assert_eq!(ids_titled(&graph, "Matter"), vec!["Song:a"]);
+    assert!(graph.find_by::<Song>("tags", "rock").is_err());
+    // A colon in the value must not bleed into the id part of the key.
+    graph.add_node(song("b", "Matter:a")).unwrap();
+    assert_eq!(ids_titled(&graph, "Matter"), vec!["Song:a"]);
+    assert!(graph.unknown_families().unwrap().is_empty());
+}
+
+#[test]
+fn index_follows_every_write_path() {
+    let dir = TestDir::new("index-write-paths");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+
+    graph.add_node(song("a", "Matter")).unwrap();
+    graph
+        .add_nodes(vec![song("b", "Matter"), song("c", "Tides")])
+        .unwrap();
+    assert_eq!(ids_titled(&graph, "Matter"), vec!["Song:a", "Song:b"]);
+
+    graph.update_node(&song("a", "Renamed")).unwrap();
+    assert_eq!(ids_titled(&graph, "Matter"), vec!["Song:b"]);
+    assert_eq!(ids_titled(&graph, "Renamed"), vec!["Song:a"]);
+
+    let txn = graph.transaction();
+    txn.update_node(&song("b", "In txn")).unwrap();
+    txn.commit().unwrap();
+    assert!(ids_titled(&graph, "Matter").is_empty());
+    assert_eq!(ids_titled(&graph, "In txn"), vec!["Song:b"]);
+
+    assert_eq!(ids_titled(&graph, "Tides"), vec!["Song:c"]);
+    graph.replace_node(song("c", "Replaced")).unwrap();
+    assert!(ids_titled(&graph, "Tides").is_empty());
+    assert_eq!(ids_titled(&graph, "Replaced"), vec!["Song:c"]);
+
+    graph
+        .import_family_jsonl(
+            "Song",
+            r#"{"id":"Song:d","in_edge_ids":[],"out_edge_ids":[],"title":"Imported","tags":[]}"#
+                .as_bytes(),
+        )
+        .unwrap();
+    assert_eq!(ids_titled(&graph, "Imported"), vec!["Song:d"]);
+
+    graph.remove_node("Song:d").unwrap();
+    assert!(ids_titled(&graph, "Imported").is_empty());
+    graph.remove_node_cascade::<Song>("Song:c").unwrap();
+    assert!(ids_titled(&graph, "Replaced").is_empty());
+
+    graph
+        .merge_nodes("Song:a", &["Song:b".to_string()])
+        .unwrap();
+    assert!(ids_titled(&graph, "In txn").is_empty());
+    assert_eq!(ids_titled(&graph, "Renamed"), vec!["Song:a"]);
+
+    graph.add_node(song("e", "Matter")).unwrap();
+    assert_eq!(
+        graph
+            .set_field_all::<Song>("title", serde_json::json!("All"))
+            .unwrap(),
+        2
+    );
+    assert_eq!(ids_titled(&graph, "All"), vec!["Song:a", "Song:e"]);
+    assert!(ids_titled(&graph, "Renamed").is_empty());
+    assert!(ids_titled(&graph, "Matter").is_empty());
+}
+
+#[test]
+fn export_subgraph_carries_the_index() {
+    let dir = TestDir::new("index-export-subgraph");
+    let graph = Graph::new(&dir.path("source")).unwrap();
+    graph.add_node(song("a", "Matter")).unwrap();
+    graph.add_node(song("b", "Matter")).unwrap();
+
+    graph
+        .export_subgraph(&["Song:a".to_string()], 0, &dir.path("subgraph"))
+        .unwrap();
+    let subgraph = Graph::new(&dir.path("subgraph")).unwrap();
+    assert_eq!(ids_titled(&subgraph, "Matter"), vec!["Song:a"]);
+}
+
+#[test]
+fn find_in_range_orders_negative_and_positive_values() {
+    let dir = TestDir::new("find-in-range");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    for (i, year) in [1999, -5, 2005, 1980, -300, 2001].iter().enumerate() {
+        graph
+            .add_node(Album::new(
+                Some(format!("a{}", i)),
+                "Matter".to_string(),
+                vec![],
+                *year,
+            ))
+            .unwrap();
+    }
+    let years = |lo, hi| -> Vec<i32> {
+        graph
+            .find_in_range::<Album>("year", lo, hi)
+            .unwrap()
+            .iter()
+            .map(|album| *album.year())
+            .collect()
+    };
+
+    assert_eq!(years(1990.0, 2002.0), vec![1999, 2001]);
+    assert_eq!(years(-1000.0, 1990.0), vec![-300, -5, 1980]);
+    assert_eq!(years(-5.0, -5.0), vec![-5]);
+    assert!(years(3000.0, 4000.0).is_empty());
+    assert_eq!(graph.find_by::<Album>("year", "2005").unwrap().len(), 1);
+    assert!(matches!(
+        graph.find_in_range::<Album>("title", 0.0, 1.0),
+        Err(GraphError::UnindexableField(_))
+    ));
+}
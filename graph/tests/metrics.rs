@@ -0,0 +1,26 @@
+#![cfg(feature = "metrics")]
+
+mod common;
+
+use common::TestDir;
+use graph::*;
+
+#[test]
+fn latency_report_counts_instrumented_operations() {
+    let dir = TestDir::new("latency-report");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    for _ in 0..2 {
+        graph.get_node::<Artist>(artist.id().to_string()).unwrap();
+    }
+
+    let report = graph.latency_report();
+    assert_eq!(report.operations["add_node"].count, 1);
+    let reads = report.operations["get_node"];
+    assert_eq!(reads.count, 2);
+    assert!(reads.p50_micros <= reads.p99_micros);
+    assert!(reads.p99_micros <= reads.max_micros);
+    assert!(!report.operations.contains_key("add_edge"));
+}
@@ -0,0 +1,695 @@
+mod common;
+
+use common::TestDir;
+use graph::*;
+
+#[test]
+fn add_node_get_id_returns_stored_id() {
+    let dir = TestDir::new("add-node-get-id");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let song_id = graph
+        .add_node_get_id(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let song: Song = graph.get_node(song_id.to_string()).unwrap();
+    assert_eq!(song.id(), &song_id);
+    assert_eq!(song.title(), "Matter");
+}
+
+#[test]
+fn byte_blob_fields_are_read_without_the_rest_of_the_node() {
+    let dir = TestDir::new("byte-blobs");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let artwork = vec![0, 1, 2, 254, 255];
+    let album = graph
+        .add_node(Album::new(
+            None,
+            "Matter".to_string(),
+            artwork.clone(),
+            2019,
+        ))
+        .unwrap();
+    assert_eq!(album.artwork(), &artwork[..]);
+
+    let album_id = album.id().to_string();
+    assert_eq!(
+        graph.get_node_field_bytes(&album_id, "artwork").unwrap(),
+        Some(artwork)
+    );
+    assert_eq!(
+        graph.get_node_field_bytes(&album_id, "missing").unwrap(),
+        None
+    );
+    assert!(graph.get_node_field_bytes("Album:nope", "artwork").is_err());
+}
+
+#[test]
+fn set_field_all_updates_every_node_in_the_family() {
+    let dir = TestDir::new("set-field-all");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    let mut song_ids = Vec::new();
+    for title in ["Matter", "Sunshine", "Floor"] {
+        let song = graph
+            .add_node(Song::new(None, title.to_string(), vec![]))
+            .unwrap();
+        song_ids.push(song.id().clone());
+    }
+    graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song_ids[0].clone(), artist.id().clone()),
+            1.0,
+        ))
+        .unwrap();
+
+    let updated = graph
+        .set_field_all::<Song>("tags", serde_json::json!(["rock"]))
+        .unwrap();
+    assert_eq!(updated, 3);
+
+    for song_id in &song_ids {
+        let song: Song = graph.get_node(song_id.to_string()).unwrap();
+        assert_eq!(song.tags(), &vec!["rock".to_string()]);
+    }
+    let song: Song = graph.get_node(song_ids[0].to_string()).unwrap();
+    assert_eq!(song.title(), "Matter");
+    assert_eq!(song.out_edge_ids().len(), 1);
+    let artist: Artist = graph.get_node(artist.id().to_string()).unwrap();
+    assert_eq!(artist.name(), "Family Stereo");
+}
+
+#[test]
+fn recent_nodes_returns_newest_first() {
+    let dir = TestDir::new("recent-nodes");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    // Keys sort the way xids do, oldest first.
+    for key in ["0001", "0002", "0003", "0004"] {
+        graph
+            .add_node(Song::new(Some(key.to_string()), key.to_string(), vec![]))
+            .unwrap();
+    }
+    graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+
+    let recent: Vec<String> = graph
+        .recent_nodes::<Song>(2)
+        .unwrap()
+        .iter()
+        .map(|song| song.title().clone())
+        .collect();
+    assert_eq!(recent, vec!["0004", "0003"]);
+    assert_eq!(graph.recent_nodes::<Song>(10).unwrap().len(), 4);
+    assert!(graph.recent_nodes::<Song>(0).unwrap().is_empty());
+}
+
+#[test]
+fn orphan_nodes_reports_only_disconnected_nodes() {
+    let dir = TestDir::new("orphan-nodes");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let connected = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let isolated = graph
+        .add_node(Song::new(None, "Floor".to_string(), vec![]))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(connected.id().clone(), artist.id().clone()),
+            1.0,
+        ))
+        .unwrap();
+
+    assert_eq!(
+        graph.orphan_nodes::<Song>().unwrap(),
+        vec![isolated.id().to_string()]
+    );
+    assert!(graph.orphan_nodes::<Artist>().unwrap().is_empty());
+}
+
+#[test]
+fn merge_nodes_keeps_the_duplicates_edges() {
+    let dir = TestDir::new("merge-nodes");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let keep = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    let duplicate = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    graph
+        .add_node(Artist::new(None, "Other".to_string(), None))
+        .unwrap();
+    let mut song_ids = Vec::new();
+    for artist in [&keep, &duplicate] {
+        let song = graph
+            .add_node(Song::new(None, "Matter".to_string(), vec![]))
+            .unwrap();
+        graph
+            .add_edge(By::new(
+                None,
+                ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+                1.0,
+            ))
+            .unwrap();
+        song_ids.push(song.id().to_string());
+    }
+
+    let duplicates = graph.find_duplicates::<Artist>("name").unwrap();
+    assert_eq!(duplicates.len(), 1);
+    let mut group = duplicates["Family Stereo"].clone();
+    group.sort();
+    let mut expected = vec![keep.id().to_string(), duplicate.id().to_string()];
+    expected.sort();
+    assert_eq!(group, expected);
+
+    graph
+        .merge_nodes(&keep.id().to_string(), &[duplicate.id().to_string()])
+        .unwrap();
+    assert!(!graph.node_exists(&duplicate.id().to_string()).unwrap());
+    let kept: Artist = graph.get_node(keep.id().to_string()).unwrap();
+    assert_eq!(kept.in_edge_ids().len(), 2);
+    for song_id in &song_ids {
+        assert_eq!(
+            graph.get_neighbors::<Song>(song_id).unwrap(),
+            vec![keep.id().to_string()]
+        );
+    }
+    assert!(graph.find_duplicates::<Artist>("name").unwrap().is_empty());
+}
+
+#[test]
+fn fold_nodes_sums_a_numeric_field() {
+    let dir = TestDir::new("fold-nodes");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    for year in [1999, 2004, 2019] {
+        graph
+            .add_node(Album::new(None, "Matter".to_string(), vec![], year))
+            .unwrap();
+    }
+    graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+
+    let total = graph
+        .fold_nodes(0, |total, album: Album| total + album.year())
+        .unwrap();
+    assert_eq!(total, 1999 + 2004 + 2019);
+    let count = graph.fold_nodes(0, |count, _: Song| count + 1).unwrap();
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn case_insensitive_families_resolve_differently_cased_ids() {
+    let dir = TestDir::new("case-insensitive");
+    let path = dir.path("db");
+    let graph = Graph::builder(&path)
+        .case_insensitive_families(true)
+        .open()
+        .unwrap();
+    let song_id = graph
+        .add_node_get_id(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap()
+        .to_string();
+    let lowercase_id = song_id.replacen("Song", "song", 1);
+    let song: Song = graph.get_node(lowercase_id.clone()).unwrap();
+    assert_eq!(song.title(), "Matter");
+    assert!(graph.node_exists(&lowercase_id).unwrap());
+    graph.close().unwrap();
+
+    let graph = Graph::new(&path).unwrap();
+    assert!(graph.get_node::<Song>(lowercase_id).is_err());
+}
+
+#[test]
+fn replace_node_keeps_existing_edges() {
+    let dir = TestDir::new("replace-node");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let song = graph
+        .add_node(Song::new(
+            Some("matter".to_string()),
+            "Matter".to_string(),
+            vec![],
+        ))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+            1.0,
+        ))
+        .unwrap();
+
+    let replacement = Song::new(
+        Some("matter".to_string()),
+        "Matter (Live)".to_string(),
+        vec![],
+    );
+    assert!(replacement.out_edge_ids().is_empty());
+    graph.replace_node(replacement).unwrap();
+
+    let stored: Song = graph.get_node(song.id().to_string()).unwrap();
+    assert_eq!(stored.title(), "Matter (Live)");
+    assert_eq!(stored.out_edge_ids().len(), 1);
+    assert_eq!(
+        graph.get_neighbors::<Song>(&song.id().to_string()).unwrap(),
+        vec![artist.id().to_string()]
+    );
+    let missing = Song::new(Some("missing".to_string()), "Floor".to_string(), vec![]);
+    assert!(matches!(
+        graph.replace_node(missing),
+        Err(GraphError::FindKeyError)
+    ));
+}
+
+#[test]
+fn get_node_rejects_an_id_from_another_family() {
+    let dir = TestDir::new("family-mismatch");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let song_id = graph
+        .add_node_get_id(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+
+    match graph.get_node::<Artist>(song_id.to_string()) {
+        Err(GraphError::FamilyMismatch { expected, found }) => {
+            assert_eq!(expected, "Artist");
+            assert_eq!(found, "Song");
+        }
+        other => panic!("expected a family mismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn prefetch_then_get_node() {
+    let dir = TestDir::new("prefetch");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let song_id = graph
+        .add_node_get_id(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap()
+        .to_string();
+    let artist_id = graph
+        .add_node_get_id(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap()
+        .to_string();
+
+    graph
+        .prefetch(&[
+            song_id.clone(),
+            artist_id.clone(),
+            "Song:missing".to_string(),
+        ])
+        .unwrap();
+    let song: Song = graph.get_node(song_id).unwrap();
+    assert_eq!(song.title(), "Matter");
+    let artist: Artist = graph.get_node(artist_id).unwrap();
+    assert_eq!(artist.name(), "Family Stereo");
+    assert!(graph.prefetch(&["Unknown:1".to_string()]).is_err());
+}
+
+#[test]
+fn distinct_field_values_deduplicates() {
+    let dir = TestDir::new("distinct-values");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    for title in ["Matter", "Floor", "Matter"] {
+        graph
+            .add_node(Song::new(None, title.to_string(), vec![]))
+            .unwrap();
+    }
+
+    let titles = graph.distinct_field_values::<Song>("title").unwrap();
+    assert_eq!(
+        titles.into_iter().collect::<Vec<_>>(),
+        vec!["Floor", "Matter"]
+    );
+    assert!(matches!(
+        graph.distinct_field_values::<Song>("missing"),
+        Err(GraphError::UnknownFieldError(_))
+    ));
+}
+
+#[test]
+fn get_all_nodes_orders_by_sort_key() {
+    let dir = TestDir::new("sort-key");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    for name in ["Charlie", "Alpha", "Bravo"] {
+        graph
+            .add_node(Artist::new(None, name.to_string(), None))
+            .unwrap();
+    }
+    let names: Vec<String> = graph
+        .get_all_nodes::<Artist>()
+        .unwrap()
+        .iter()
+        .map(|artist| artist.name().clone())
+        .collect();
+    assert_eq!(names, vec!["Alpha", "Bravo", "Charlie"]);
+
+    // Without `sort_by` the key falls back to the id.
+    let song = Song::new(None, "Matter".to_string(), vec![]);
+    assert_eq!(song.sort_key(), song.id().to_string());
+}
+
+#[test]
+fn node_cache_is_invalidated_on_every_write_path() {
+    let dir = TestDir::new("node-cache");
+    let graph = Graph::builder(&dir.path("db"))
+        .node_cache(16)
+        .open()
+        .unwrap();
+    let id = "Song:cached".to_string();
+    let song = |title: &str| Song::new(Some("cached".to_string()), title.to_string(), vec![]);
+    let title = || graph.get_node::<Song>(id.clone()).unwrap().title().clone();
+
+    graph.add_node(song("added")).unwrap();
+    assert_eq!(title(), "added");
+
+    graph.update_node(&song("updated")).unwrap();
+    assert_eq!(title(), "updated");
+
+    let txn = graph.transaction();
+    txn.update_node(&song("in txn")).unwrap();
+    assert_eq!(title(), "updated");
+    txn.commit().unwrap();
+    assert_eq!(title(), "in txn");
+
+    graph.add_nodes(vec![song("batch")]).unwrap();
+    assert_eq!(title(), "batch");
+
+    graph
+        .set_field_all::<Song>("title", serde_json::json!("everywhere"))
+        .unwrap();
+    assert_eq!(title(), "everywhere");
+
+    graph.remove_node(&id).unwrap();
+    assert!(graph.get_node::<Song>(id.clone()).is_err());
+}
+
+#[test]
+fn reserve_ids_generates_unique_ids_without_writing() {
+    let dir = TestDir::new("reserve-ids");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let ids: std::collections::HashSet<String> = graph
+        .reserve_ids::<Album>(50)
+        .iter()
+        .map(|id| id.to_string())
+        .collect();
+    assert_eq!(ids.len(), 50);
+    assert!(ids.iter().all(|id| id.starts_with("Album:")));
+    assert!(graph.is_empty().unwrap());
+}
+
+#[test]
+fn append_to_field_pushes_onto_arrays_only() {
+    let dir = TestDir::new("append-to-field");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let id = graph
+        .add_node_get_id(Song::new(
+            None,
+            "Matter".to_string(),
+            vec!["rock".to_string()],
+        ))
+        .unwrap()
+        .to_string();
+
+    graph
+        .append_to_field(&id, "tags", serde_json::json!("live"))
+        .unwrap();
+    let song: Song = graph.get_node(id.clone()).unwrap();
+    assert_eq!(song.tags(), &vec!["rock".to_string(), "live".to_string()]);
+
+    assert!(graph
+        .append_to_field(&id, "title", serde_json::json!("live"))
+        .is_err());
+    assert!(graph
+        .append_to_field(&id, "moods", serde_json::json!("live"))
+        .is_err());
+    let song: Song = graph.get_node(id).unwrap();
+    assert_eq!(song.title(), "Matter");
+}
+
+#[test]
+fn add_nodes_writes_the_whole_batch() {
+    let dir = TestDir::new("add-nodes");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let songs: Vec<Song> = (0..1_000)
+        .map(|i| Song::new(None, format!("Track {}", i), vec![]))
+        .collect();
+    graph.add_nodes(songs.clone()).unwrap();
+
+    assert_eq!(graph.count_nodes_in_family("Song").unwrap(), 1_000);
+    let last: Song = graph.get_node(songs[999].id().to_string()).unwrap();
+    assert_eq!(last.title(), "Track 999");
+}
+
+#[test]
+fn iter_nodes_decodes_lazily() {
+    let dir = TestDir::new("iter-nodes");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    graph
+        .add_nodes(
+            (0..5)
+                .map(|i| Song::new(None, format!("Track {}", i), vec![]))
+                .collect(),
+        )
+        .unwrap();
+
+    let songs: Vec<Song> = graph
+        .iter_nodes::<Song>("Song")
+        .unwrap()
+        .take(3)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(songs.len(), 3);
+    // Decoding into the wrong type fails per item, not up front.
+    assert!(graph
+        .iter_nodes::<Album>("Song")
+        .unwrap()
+        .next()
+        .unwrap()
+        .is_err());
+}
+
+#[test]
+fn scan_nodes_pages_through_a_family_with_a_cursor() {
+    let dir = TestDir::new("scan-nodes");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    graph
+        .add_nodes(
+            (0..25)
+                .map(|i| Song::new(None, format!("Track {}", i), vec![]))
+                .collect(),
+        )
+        .unwrap();
+
+    let mut cursor: Option<String> = None;
+    let mut page_sizes = Vec::new();
+    let mut ids = Vec::new();
+    loop {
+        let page = graph
+            .scan_nodes::<Song>("Song", cursor.as_deref(), 10)
+            .unwrap();
+        if page.is_empty() {
+            break;
+        }
+        page_sizes.push(page.len());
+        cursor = Some(page.last().unwrap().id().to_string());
+        ids.extend(page.iter().map(|song| song.id().to_string()));
+    }
+
+    assert_eq!(page_sizes, vec![10, 10, 5]);
+    let mut sorted = ids.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(ids, sorted);
+}
+
+#[test]
+fn family_mismatch_is_caught_before_the_lookup() {
+    let dir = TestDir::new("family-mismatch-missing");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+
+    // No such key either way; the family check wins over FindKeyError.
+    assert!(matches!(
+        graph.get_node::<Artist>("Song:missing".to_string()),
+        Err(GraphError::FamilyMismatch { .. })
+    ));
+    assert_eq!(Song::new_id("missing".to_string()).family_name(), "Song");
+}
+
+#[test]
+fn node_exists_and_try_get_node_tell_missing_from_wrong() {
+    let dir = TestDir::new("node-exists");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let id = graph
+        .add_node_get_id(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap()
+        .to_string();
+
+    assert!(graph.node_exists(&id).unwrap());
+    assert!(!graph.node_exists("Song:missing").unwrap());
+    assert!(!graph.node_exists("Label:missing").unwrap());
+    assert!(graph.try_get_node::<Song>(&id).unwrap().is_some());
+    assert!(graph
+        .try_get_node::<Song>("Song:missing")
+        .unwrap()
+        .is_none());
+    assert!(graph.try_get_node::<Artist>(&id).is_err());
+}
+
+#[test]
+fn get_any_node_dispatches_on_the_id_family() {
+    let dir = TestDir::new("get-any-node");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let id = graph
+        .add_node_get_id(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap()
+        .to_string();
+
+    let node = graph.get_any_node(&id).unwrap();
+    assert_eq!(node.family_name(), "Song");
+    assert_eq!(node.id(), id);
+    match node {
+        AnyNode::Song(song) => assert_eq!(song.title(), "Matter"),
+        other => panic!("expected a Song, got {:?}", other),
+    }
+    assert!(matches!(
+        graph.get_any_node("Song:missing"),
+        Err(GraphError::FindKeyError)
+    ));
+    assert!(matches!(
+        graph.get_any_node("Label:missing"),
+        Err(GraphError::FindFamilyError)
+    ));
+}
+
+#[test]
+fn get_nodes_keeps_request_order_and_gaps() {
+    let dir = TestDir::new("get-nodes");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let add_song = |title: &str| {
+        graph
+            .add_node_get_id(Song::new(None, title.to_string(), vec![]))
+            .unwrap()
+            .to_string()
+    };
+    let (first, second) = (add_song("Matter"), add_song("Tides"));
+    let artist = graph
+        .add_node_get_id(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap()
+        .to_string();
+
+    let songs = graph
+        .get_nodes::<Song>(&[second, "Song:missing".to_string(), first.clone()])
+        .unwrap();
+    let titles: Vec<Option<&str>> = songs
+        .iter()
+        .map(|song| song.as_ref().map(|song| song.title().as_str()))
+        .collect();
+    assert_eq!(titles, vec![Some("Tides"), None, Some("Matter")]);
+    assert!(graph
+        .get_nodes::<Song>(std::slice::from_ref(&artist))
+        .is_err());
+
+    let any = graph
+        .get_any_nodes(&[
+            artist,
+            "Artist:missing".to_string(),
+            first,
+            "Label:x".to_string(),
+        ])
+        .unwrap();
+    assert!(matches!(any[0], Some(AnyNode::Artist(_))));
+    assert!(any[1].is_none());
+    assert!(matches!(any[2], Some(AnyNode::Song(_))));
+    assert!(any[3].is_none());
+}
+
+#[test]
+fn keys_may_contain_colons() {
+    let dir = TestDir::new("colon-keys");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let song = graph
+        .add_node(Song::new(
+            Some("urn:isrc:GB1234".to_string()),
+            "Matter".to_string(),
+            vec![],
+        ))
+        .unwrap();
+    let id = song.id().to_string();
+    assert_eq!(id, "Song:urn:isrc:GB1234");
+
+    let stored: Song = graph.get_node(id.clone()).unwrap();
+    assert_eq!(stored.title(), "Matter");
+    assert!(graph.node_exists(&id).unwrap());
+    assert_eq!(graph.get_any_node(&id).unwrap().family_name(), "Song");
+}
+
+#[test]
+fn malformed_ids_are_rejected() {
+    let dir = TestDir::new("malformed-ids");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+
+    for id in ["Song", "Song:", ":abc", ""] {
+        assert!(
+            matches!(
+                graph.get_node::<Song>(id.to_string()),
+                Err(GraphError::ParseNodeIdError)
+            ),
+            "{}",
+            id
+        );
+        assert!(
+            matches!(graph.get_any_node(id), Err(GraphError::ParseNodeIdError)),
+            "{}",
+            id
+        );
+    }
+    assert!(graph
+        .get_node::<Song>(format!("{}:extra", song.id()))
+        .is_err());
+}
+
+#[test]
+fn nodes_with_id_prefix_stops_at_the_end_of_the_prefix() {
+    let dir = TestDir::new("id-prefix");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    for key in ["ab1", "ab2", "ac1", "b1"] {
+        graph
+            .add_node(Song::new(Some(key.to_string()), key.to_string(), vec![]))
+            .unwrap();
+    }
+    graph
+        .add_node(Artist::new(
+            Some("ab9".to_string()),
+            "Other".to_string(),
+            None,
+        ))
+        .unwrap();
+
+    let ids: Vec<String> = graph
+        .nodes_with_id_prefix::<Song>("Song", "Song:ab")
+        .unwrap()
+        .iter()
+        .map(|song| song.id().to_string())
+        .collect();
+    assert_eq!(ids, vec!["Song:ab1", "Song:ab2"]);
+    assert!(graph
+        .nodes_with_id_prefix::<Song>("Song", "Song:z")
+        .unwrap()
+        .is_empty());
+    assert!(graph
+        .nodes_with_id_prefix::<Song>("Artist", "Artist:")
+        .is_err());
+}
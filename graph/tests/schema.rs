@@ -0,0 +1,312 @@
+mod common;
+
+use common::TestDir;
+use graph::*;
+use std::thread::sleep;
+use std::time::Duration;
+
+#[test]
+fn field_less_nodes_and_edges_round_trip() {
+    let dir = TestDir::new("field-less");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let playlist = graph.add_node(Playlist::new(None)).unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    assert!(Playlist::field_names().is_empty());
+
+    let stored: Playlist = graph.get_node(playlist.id().to_string()).unwrap();
+    assert_eq!(stored.id(), playlist.id());
+    let parsed: Playlist = serde_json::to_string(&stored).unwrap().parse().unwrap();
+    assert_eq!(parsed.id(), playlist.id());
+
+    let edge = graph
+        .add_edge(Contains::new(
+            None,
+            ContainsConnection::PlaylistContainsSong(playlist.id().clone(), song.id().clone()),
+        ))
+        .unwrap();
+    let stored: Contains = graph.get_edge(edge.id().clone()).unwrap();
+    assert_eq!(stored.id(), edge.id());
+    let decoded = Contains::from_msgpack(&stored.to_msgpack().unwrap()).unwrap();
+    assert_eq!(decoded.id(), edge.id());
+    let parsed: Contains = serde_json::to_string(&stored).unwrap().parse().unwrap();
+    assert_eq!(
+        parsed.connection().endpoints(),
+        (playlist.id().to_string(), song.id().to_string())
+    );
+}
+
+#[test]
+fn all_defaulted_nodes_implement_default() {
+    let artist = Artist::default();
+    assert_eq!(artist.name(), "");
+    assert_eq!(artist.bio(), &None);
+    assert!(artist.id().to_string().starts_with("Artist:"));
+    assert!(artist.in_edge_ids().is_empty());
+    assert_ne!(Artist::default().id(), artist.id());
+}
+
+#[test]
+fn validate_node_json_checks_fields_and_types() {
+    let dir = TestDir::new("validate-node-json");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let valid = serde_json::json!({
+        "id": "Album:x", "in_edge_ids": [], "out_edge_ids": [],
+        "title": "Matter", "artwork": [1, 2], "year": 2019
+    });
+    graph.validate_node_json("Album", &valid).unwrap();
+
+    let missing = serde_json::json!({
+        "id": "Album:x", "in_edge_ids": [], "out_edge_ids": [], "title": "Matter"
+    });
+    assert!(matches!(
+        graph.validate_node_json("Album", &missing),
+        Err(GraphError::InvalidNodeError(_))
+    ));
+
+    let mut extra = valid.clone();
+    extra["label"] = serde_json::json!("Sub Pop");
+    assert!(matches!(
+        graph.validate_node_json("Album", &extra),
+        Err(GraphError::UnknownFieldError(_))
+    ));
+
+    let mut mistyped = valid.clone();
+    mistyped["title"] = serde_json::json!(3);
+    assert!(graph.validate_node_json("Album", &mistyped).is_err());
+
+    assert!(graph.validate_node_json("Label", &valid).is_err());
+}
+
+#[test]
+fn option_and_vec_fields_round_trip() {
+    let dir = TestDir::new("composite-fields");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let artist = graph
+        .add_node(Artist::new(
+            None,
+            "Family Stereo".to_string(),
+            Some("From Leeds".to_string()),
+        ))
+        .unwrap();
+    let song = graph
+        .add_node(Song::new(
+            None,
+            "Matter".to_string(),
+            vec!["rock".to_string(), "live".to_string()],
+        ))
+        .unwrap();
+
+    let stored: Artist = graph.get_node(artist.id().to_string()).unwrap();
+    assert_eq!(stored.bio(), &Some("From Leeds".to_string()));
+    let stored: Song = graph.get_node(song.id().to_string()).unwrap();
+    assert_eq!(stored.tags(), &vec!["rock".to_string(), "live".to_string()]);
+}
+
+#[test]
+fn defaulted_fields_decode_from_older_records() {
+    let dir = TestDir::new("defaulted-fields");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    // Written before `name` and `bio` were part of the schema.
+    graph
+        .import_family_jsonl(
+            "Artist",
+            r#"{"id":"Artist:old","in_edge_ids":[],"out_edge_ids":[]}"#.as_bytes(),
+        )
+        .unwrap();
+
+    let artist: Artist = graph.get_node("Artist:old".to_string()).unwrap();
+    assert_eq!(artist.name(), "");
+    assert_eq!(artist.bio(), &None);
+}
+
+#[test]
+fn timestamps_are_set_on_create_and_bumped_on_write() {
+    let dir = TestDir::new("timestamps");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let playlist = graph
+        .add_node(Playlist::new(Some("p".to_string())))
+        .unwrap();
+    let id = playlist.id().to_string();
+    let created = playlist.created_at().unwrap();
+    assert_eq!(playlist.updated_at(), Some(created));
+    let tick = || sleep(Duration::from_millis(5));
+
+    tick();
+    let mutated = graph.mutate_node::<Playlist, _>(&id, |_| {}).unwrap();
+    assert_eq!(mutated.created_at(), Some(created));
+    assert!(mutated.updated_at().unwrap() > created);
+    let stored: Playlist = graph.get_node(id.clone()).unwrap();
+    assert_eq!(stored.updated_at(), mutated.updated_at());
+
+    tick();
+    graph.update_node(&stored).unwrap();
+    let updated: Playlist = graph.get_node(id.clone()).unwrap();
+    assert!(updated.updated_at() > stored.updated_at());
+    assert_eq!(updated.created_at(), Some(created));
+
+    // A replacement is built fresh, but keeps the original creation time.
+    tick();
+    graph
+        .replace_node(Playlist::new(Some("p".to_string())))
+        .unwrap();
+    let replaced: Playlist = graph.get_node(id).unwrap();
+    assert_eq!(replaced.created_at(), Some(created));
+    assert!(replaced.updated_at() > updated.updated_at());
+
+    assert_eq!(
+        Song::new(None, "Matter".to_string(), vec![]).created_at(),
+        None
+    );
+}
+
+#[test]
+fn validate_node_json_expects_timestamp_fields() {
+    let dir = TestDir::new("validate-timestamps");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let mut playlist = serde_json::json!({
+        "id": "Playlist:p", "in_edge_ids": [], "out_edge_ids": [],
+        "created_at": 1, "updated_at": 2
+    });
+    graph.validate_node_json("Playlist", &playlist).unwrap();
+
+    playlist["updated_at"] = serde_json::json!("yesterday");
+    assert!(graph.validate_node_json("Playlist", &playlist).is_err());
+    playlist.as_object_mut().unwrap().remove("updated_at");
+    assert!(matches!(
+        graph.validate_node_json("Playlist", &playlist),
+        Err(GraphError::InvalidNodeError(_))
+    ));
+}
+
+#[test]
+fn builders_require_fields_without_defaults() {
+    let song = Song::builder()
+        .with_title("Matter".to_string())
+        .with_tags(vec![])
+        .build()
+        .unwrap();
+    assert_eq!(song.title(), "Matter");
+    assert!(song.id().to_string().starts_with("Song:"));
+    assert!(matches!(
+        Song::builder().with_tags(vec![]).build(),
+        Err(GraphError::MissingFieldError(field)) if field == "title"
+    ));
+
+    let artist = Artist::builder().with_id("x".to_string()).build().unwrap();
+    assert_eq!(artist.id().to_string(), "Artist:x");
+    assert_eq!(artist.bio(), &None);
+    let artist = Artist::builder()
+        .with_bio("From Leeds".to_string())
+        .build()
+        .unwrap();
+    assert_eq!(artist.bio(), &Some("From Leeds".to_string()));
+}
+
+#[test]
+fn family_names_are_generated_constants() {
+    assert_eq!(Song::family_name_static(), "Song");
+    assert_eq!(<By as Edge>::FAMILY, "By");
+    assert_eq!(CollaboratedWith::family_name_static(), "CollaboratedWith");
+    assert_eq!(
+        Song::new(None, "Matter".to_string(), vec![]).family_name(),
+        Song::FAMILY
+    );
+}
+
+#[test]
+fn msgpack_helpers_match_the_stored_bytes() {
+    let dir = TestDir::new("msgpack");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let song = graph
+        .add_node(Song::new(
+            None,
+            "Matter".to_string(),
+            vec!["rock".to_string()],
+        ))
+        .unwrap();
+
+    let (_, bytes) = graph.scan_raw("Song", 1).unwrap().remove(0);
+    assert_eq!(song.to_msgpack().unwrap(), bytes);
+    assert_eq!(Song::from_msgpack(&bytes).unwrap().title(), "Matter");
+    // `FromStr` stays JSON, for text from outside the graph.
+    let parsed: Song = serde_json::to_string(&song).unwrap().parse().unwrap();
+    assert_eq!(parsed.id(), song.id());
+
+    let edge = By::new(
+        None,
+        ByConnection::SongIsBy(song.id().clone(), ArtistId::new(None)),
+        2.0,
+    );
+    let decoded = By::from_msgpack(&edge.to_msgpack().unwrap()).unwrap();
+    assert_eq!(*decoded.weight(), 2.0);
+}
+
+#[test]
+fn nodes_and_edges_compare_and_hash() {
+    let dir = TestDir::new("hash-nodes");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let matter = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let tides = graph
+        .add_node(Song::new(None, "Tides".to_string(), vec![]))
+        .unwrap();
+
+    let loaded: Song = graph.get_node(matter.id().to_string()).unwrap();
+    assert_eq!(loaded, matter);
+    let songs: std::collections::HashSet<Song> =
+        [matter.clone(), loaded, tides].into_iter().collect();
+    assert_eq!(songs.len(), 2);
+
+    // `By` has an f64 field, so it gets PartialEq but not Hash.
+    let edge = By::new(
+        None,
+        ByConnection::SongIsBy(matter.id().clone(), ArtistId::new(None)),
+        1.0,
+    );
+    assert_eq!(edge.clone(), edge);
+    let ids: std::collections::HashSet<ById> = [edge.id().clone()].into_iter().collect();
+    assert!(ids.contains(edge.id()));
+}
+
+#[test]
+fn ids_display_as_their_inner_string() {
+    let song_id = Song::new_id("abc".to_string());
+    assert_eq!(format!("{}", song_id), "Song:abc");
+    assert_eq!(song_id.to_string(), format!("{song_id}"));
+    let edge = By::new(
+        Some("e".to_string()),
+        ByConnection::SongIsBy(song_id, ArtistId::new(None)),
+        1.0,
+    );
+    assert_eq!(format!("{}", edge.id()), "By:e");
+}
+
+#[test]
+fn ids_parse_from_family_prefixed_strings() {
+    let song_id: SongId = "Song:cn3f0cr6n88fvhf2lbj0".parse().unwrap();
+    assert_eq!(song_id, Song::new_id("cn3f0cr6n88fvhf2lbj0".to_string()));
+    let song = Song::new(None, "Matter".to_string(), vec![]);
+    assert_eq!(&song.id().to_string().parse::<SongId>().unwrap(), song.id());
+    // Keys may hold colons; only the first one separates the family.
+    assert_eq!(
+        "Song:urn:isrc:1".parse::<SongId>().unwrap(),
+        Song::new_id("urn:isrc:1".to_string())
+    );
+
+    assert!(matches!(
+        "Artist:abc".parse::<SongId>(),
+        Err(GraphError::FamilyMismatch { .. })
+    ));
+    for bad in ["Song", "Song:", ":abc", "abc"] {
+        assert!(
+            matches!(bad.parse::<SongId>(), Err(GraphError::ParseNodeIdError)),
+            "{}",
+            bad
+        );
+    }
+    assert_eq!("By:e".parse::<ById>().unwrap().to_string(), "By:e");
+    assert!("Contains:e".parse::<ById>().is_err());
+}
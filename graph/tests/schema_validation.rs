@@ -0,0 +1,101 @@
+#[allow(dead_code)]
+#[path = "../build/schema.rs"]
+mod schema;
+
+use schema::{schema_errors, Schema};
+use serde_json::json;
+
+fn errors_for(schema: serde_json::Value) -> Vec<String> {
+    let schema: Schema = serde_json::from_value(schema).unwrap();
+    schema_errors(&schema)
+}
+
+fn artist() -> serde_json::Value {
+    json!({"name": "Artist", "fields": [{"name": "name", "type": "String"}]})
+}
+
+#[test]
+fn well_formed_schema_has_no_errors() {
+    let schema = json!({
+        "nodes": [artist(), {"name": "Song"}],
+        "edges": [{
+            "name": "By",
+            "connections": [{"from": "Song", "to": "Artist", "name": "SongIsBy"}]
+        }]
+    });
+    assert!(errors_for(schema).is_empty());
+}
+
+#[test]
+fn unknown_connection_endpoints_are_reported() {
+    let schema = json!({
+        "nodes": [artist()],
+        "edges": [{
+            "name": "By",
+            "connections": [{"from": "Song", "to": "Artist", "name": "SongIsBy"}]
+        }]
+    });
+    assert_eq!(
+        errors_for(schema),
+        vec!["edge `By` connection `SongIsBy` references unknown node `Song`"]
+    );
+}
+
+#[test]
+fn reused_names_are_reported() {
+    let schema = json!({
+        "nodes": [artist(), artist()],
+        "edges": [
+            {"name": "Artist", "connections": []},
+            {
+                "name": "Follows",
+                "connections": [
+                    {"from": "Artist", "to": "Artist", "name": "ArtistFollows"},
+                    {"from": "Artist", "to": "Artist", "name": "ArtistFollows"}
+                ]
+            }
+        ]
+    });
+    assert_eq!(
+        errors_for(schema),
+        vec![
+            "node `Artist` reuses the name of an earlier node",
+            "edge `Artist` reuses the name of an earlier node",
+            "edge `Follows` declares connection `ArtistFollows` more than once",
+        ]
+    );
+}
+
+#[test]
+fn sort_by_must_name_a_displayable_field() {
+    let schema = json!({
+        "nodes": [
+            {"name": "Artist", "sort_by": "rank", "fields": []},
+            {
+                "name": "Album",
+                "sort_by": "artwork",
+                "fields": [{"name": "artwork", "type": "Vec<u8>"}]
+            }
+        ],
+        "edges": []
+    });
+    assert_eq!(
+        errors_for(schema),
+        vec![
+            "node `Artist` sorts by unknown field `rank`",
+            "node `Album` sorts by field `artwork` of type `Vec<u8>`, which has no string form",
+        ]
+    );
+}
+
+#[test]
+fn zero_length_prefix_extractor_is_reported() {
+    let schema = json!({
+        "nodes": [{"name": "Song", "prefix_extractor": 0}, artist()],
+        "edges": []
+    });
+    assert_eq!(
+        errors_for(schema),
+        vec!["node `Song` has a prefix_extractor of 0, which matches every key"]
+    );
+}
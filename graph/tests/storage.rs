@@ -0,0 +1,499 @@
+mod common;
+
+use common::TestDir;
+use graph::*;
+
+#[test]
+fn strict_families_refuses_to_create_missing_families() {
+    let dir = TestDir::new("strict-families");
+    let path = dir.path("db");
+    assert!(matches!(
+        Graph::builder(&path).strict_families(true).open(),
+        Err(GraphError::MissingFamilyError(_))
+    ));
+
+    Graph::new(&path).unwrap().close().unwrap();
+    let graph = Graph::builder(&path).strict_families(true).open().unwrap();
+    graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let record = r#"{"id":"Extra:1"}"#;
+    assert!(matches!(
+        graph.import_family_jsonl("Extra", record.as_bytes()),
+        Err(GraphError::MissingFamilyError(family)) if family == "Extra"
+    ));
+}
+
+#[test]
+fn custom_wal_dir_keeps_writes_across_reopen() {
+    let dir = TestDir::new("wal-dir");
+    let path = dir.path("db");
+    let wal_dir = dir.path("wal");
+    let song_id = {
+        let graph = Graph::builder(&path)
+            .wal_dir(&wal_dir)
+            .max_total_wal_size(1 << 20)
+            .open()
+            .unwrap();
+        let song_id = graph
+            .add_node_get_id(Song::new(None, "Matter".to_string(), vec![]))
+            .unwrap();
+        graph.close().unwrap();
+        song_id
+    };
+
+    let graph = Graph::builder(&path).wal_dir(&wal_dir).open().unwrap();
+    let song: Song = graph.get_node(song_id.to_string()).unwrap();
+    assert_eq!(song.title(), "Matter");
+}
+
+#[test]
+fn is_empty_until_a_node_is_added() {
+    let dir = TestDir::new("is-empty");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    assert!(graph.is_empty().unwrap());
+    graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    assert!(!graph.is_empty().unwrap());
+}
+
+#[test]
+fn universal_compaction_handles_a_bulk_insert() {
+    let dir = TestDir::new("universal-compaction");
+    let graph = Graph::builder(&dir.path("db"))
+        .compaction_style(DBCompactionStyle::Universal)
+        .level_zero_file_num_compaction_trigger(8)
+        .open()
+        .unwrap();
+    let songs = (0..2000)
+        .map(|i| Song::new(None, format!("Song {}", i), vec![]))
+        .collect();
+    graph.add_nodes(songs).unwrap();
+
+    assert_eq!(graph.count_nodes_in_family("Song").unwrap(), 2000);
+    let options = graph.family_options("Song").unwrap().unwrap();
+    assert_eq!(options.compaction_style, Some(DBCompactionStyle::Universal));
+    assert_eq!(options.level_zero_file_num_compaction_trigger, Some(8));
+}
+
+#[test]
+fn family_options_survive_reopen() {
+    let dir = TestDir::new("family-options-reopen");
+    let path = dir.path("db");
+    let options = FamilyOptions {
+        compression: Some(Compression::Zstd),
+        prefix_length: Some(4),
+        ..FamilyOptions::default()
+    };
+    {
+        let graph = Graph::new(&path).unwrap();
+        graph.set_family_options("Extra", options).unwrap();
+        graph
+            .import_family_jsonl("Extra", r#"{"id":"Extra:1"}"#.as_bytes())
+            .unwrap();
+        graph.close().unwrap();
+    }
+
+    let graph = Graph::builder(&path)
+        .compaction_style(DBCompactionStyle::Universal)
+        .open()
+        .unwrap();
+    assert_eq!(graph.family_options("Extra").unwrap(), Some(options));
+    assert_eq!(graph.scan_raw("Extra", 10).unwrap().len(), 1);
+}
+
+#[test]
+fn memory_usage_reports_block_cache_reads() {
+    let dir = TestDir::new("memory-usage");
+    let path = dir.path("db");
+    let song_id = {
+        let graph = Graph::new(&path).unwrap();
+        let song_id = graph
+            .add_node_get_id(Song::new(None, "Cached".to_string(), vec![]))
+            .unwrap();
+        graph.close().unwrap();
+        song_id
+    };
+
+    // Reopening flushes the recovered WAL, so the read below goes through
+    // the block cache rather than the memtable.
+    let graph = Graph::builder(&path)
+        .block_cache_size(1 << 20)
+        .open()
+        .unwrap();
+    let _: Song = graph.get_node(song_id.to_string()).unwrap();
+    let usage = graph.memory_usage().unwrap();
+    assert!(usage.block_cache_usage > 0);
+    assert!(usage.block_cache_pinned_usage <= usage.block_cache_usage);
+}
+
+#[test]
+fn unknown_families_lists_families_outside_the_schema() {
+    let dir = TestDir::new("unknown-families");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    assert!(graph.unknown_families().unwrap().is_empty());
+
+    graph
+        .import_family_jsonl("Legacy", r#"{"id":"Legacy:1"}"#.as_bytes())
+        .unwrap();
+    assert_eq!(
+        graph.unknown_families().unwrap(),
+        vec!["Legacy".to_string()]
+    );
+}
+
+#[test]
+fn clone_to_returns_an_independent_copy() {
+    let dir = TestDir::new("clone-to");
+    let graph = Graph::new(&dir.path("source")).unwrap();
+    graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+
+    let copy = graph.clone_to(&dir.path("copy")).unwrap();
+    assert_eq!(copy.count_nodes().unwrap(), 2);
+    copy.add_node(Song::new(None, "Tides".to_string(), vec![]))
+        .unwrap();
+    assert_eq!(copy.count_nodes().unwrap(), 3);
+    assert_eq!(graph.count_nodes().unwrap(), 2);
+}
+
+#[test]
+fn clone_to_keeps_family_options_and_config() {
+    let dir = TestDir::new("clone-to-options");
+    let config = GraphConfig {
+        compression: Compression::Zstd,
+        ..GraphConfig::default()
+    };
+    let graph = Graph::builder(&dir.path("source"))
+        .config(config)
+        .open()
+        .unwrap();
+    let options = FamilyOptions {
+        prefix_length: Some(3),
+        ..FamilyOptions::default()
+    };
+    graph.set_family_options("Album", options).unwrap();
+    graph.set_family_options("Extra", options).unwrap();
+
+    let copy = graph.clone_to(&dir.path("copy")).unwrap();
+    for family_name in ["Album", "Extra", "Song"] {
+        assert_eq!(
+            copy.family_options(family_name).unwrap(),
+            graph.family_options(family_name).unwrap()
+        );
+    }
+    copy.set_family_options("Label", FamilyOptions::default())
+        .unwrap();
+    assert_eq!(
+        copy.family_options("Label").unwrap().unwrap().compression,
+        Some(Compression::Zstd)
+    );
+}
+
+#[test]
+fn count_nodes_leaves_out_edges() {
+    let dir = TestDir::new("count-nodes");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    graph.add_node(Playlist::new(None)).unwrap();
+    graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+            1.0,
+        ))
+        .unwrap();
+
+    assert_eq!(graph.count_nodes().unwrap(), 3);
+    assert_eq!(graph.count_edges().unwrap(), 1);
+}
+
+#[test]
+fn count_nodes_in_family_counts_one_family() {
+    let dir = TestDir::new("count-nodes-in-family");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    for title in ["Matter", "Tides"] {
+        graph
+            .add_node(Song::new(None, title.to_string(), vec![]))
+            .unwrap();
+    }
+
+    assert_eq!(graph.count_nodes_in_family("Song").unwrap(), 2);
+    assert_eq!(graph.count_nodes_in_family("Artist").unwrap(), 0);
+    assert!(matches!(
+        graph.count_nodes_in_family("Label"),
+        Err(GraphError::FindFamilyError)
+    ));
+}
+
+#[test]
+fn opening_a_corrupt_database_is_an_error() {
+    let dir = TestDir::new("corrupt");
+    let path = dir.path("db");
+    std::fs::create_dir_all(&path).unwrap();
+    std::fs::write(format!("{}/CURRENT", path), "garbage").unwrap();
+
+    assert!(matches!(
+        Graph::new(&path),
+        Err(GraphError::Rocks {
+            op: Operation::OpenDb,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn close_refuses_while_other_handles_are_open() {
+    let dir = TestDir::new("close");
+    let path = dir.path("db");
+    let graph = Graph::new(&path).unwrap();
+    graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+
+    let handle = graph.clone();
+    assert!(matches!(graph.close(), Err(GraphError::DbNotClosed)));
+    handle.destroy_on_disk().unwrap();
+
+    let graph = Graph::new(&path).unwrap();
+    assert_eq!(graph.count_nodes().unwrap(), 0);
+    graph.close().unwrap();
+}
+
+#[test]
+fn family_compression_is_pinned_from_the_config() {
+    let dir = TestDir::new("with-options");
+    let path = dir.path("db");
+    let config = GraphConfig {
+        parallelism: 4,
+        compression: Compression::Zstd,
+        ..GraphConfig::default()
+    };
+    {
+        let graph = Graph::with_options(&path, config).unwrap();
+        graph
+            .set_family_options("Extra", FamilyOptions::default())
+            .unwrap();
+        graph
+            .add_node(Song::new(None, "Matter".to_string(), vec![]))
+            .unwrap();
+        graph.close().unwrap();
+    }
+
+    // Reopening with a different compression leaves the family as created.
+    let graph = Graph::new(&path).unwrap();
+    assert_ne!(GraphConfig::default().compression, Compression::Zstd);
+    assert_eq!(
+        graph.family_options("Extra").unwrap().unwrap().compression,
+        Some(Compression::Zstd)
+    );
+    assert_eq!(graph.count_nodes().unwrap(), 1);
+}
+
+#[test]
+fn schema_recommends_a_prefix_extractor_per_family() {
+    assert_eq!(family_prefix_length("Song"), Some("Song:".len() + 4));
+    assert_eq!(family_prefix_length("Album"), None);
+
+    let dir = TestDir::new("family-prefix");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let options = FamilyOptions {
+        prefix_length: Some(3),
+        ..FamilyOptions::default()
+    };
+    graph.set_family_options("Album", options).unwrap();
+    assert_eq!(
+        graph
+            .family_options("Album")
+            .unwrap()
+            .unwrap()
+            .prefix_length,
+        Some(3)
+    );
+    assert!(graph.set_family_options("__meta__", options).is_err());
+}
+
+#[test]
+fn compaction_runs_on_a_closed_graph() {
+    let dir = TestDir::new("compaction");
+    let path = dir.path("db");
+    let graph = Graph::new(&path).unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let keep = graph
+        .add_node(Song::new(None, "Tides".to_string(), vec![]))
+        .unwrap();
+    graph.remove_node(&song.id().to_string()).unwrap();
+    graph.compact_family("Song").unwrap();
+
+    let graph = Graph::new(&path).unwrap();
+    assert!(matches!(
+        graph.clone().compact_all(),
+        Err(GraphError::DbNotClosed)
+    ));
+    graph.compact_all().unwrap();
+
+    let graph = Graph::new(&path).unwrap();
+    assert_eq!(graph.count_nodes().unwrap(), 1);
+    assert!(graph.node_exists(&keep.id().to_string()).unwrap());
+    assert!(matches!(
+        graph.compact_family("Label"),
+        Err(GraphError::FindFamilyError)
+    ));
+}
+
+#[test]
+fn backup_restores_into_a_new_path() {
+    let dir = TestDir::new("backup");
+    let graph = Graph::new(&dir.path("source")).unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+            1.0,
+        ))
+        .unwrap();
+
+    let backup_dir = dir.path("backup");
+    graph.backup_to(&backup_dir).unwrap();
+    graph.destroy_on_disk().unwrap();
+
+    let restored = Graph::restore_from(&backup_dir, &dir.path("restored")).unwrap();
+    let song: Song = restored.get_node(song.id().to_string()).unwrap();
+    assert_eq!(song.title(), "Matter");
+    assert_eq!(song.out_edge_ids().len(), 1);
+    assert!(restored.node_exists(&artist.id().to_string()).unwrap());
+    assert_eq!(restored.count_edges().unwrap(), 1);
+}
+
+#[test]
+fn backup_keeps_family_options() {
+    let dir = TestDir::new("backup-options");
+    let graph = Graph::new(&dir.path("source")).unwrap();
+    let options = FamilyOptions {
+        prefix_length: Some(3),
+        compression: Some(Compression::Lz4),
+        ..FamilyOptions::default()
+    };
+    graph.set_family_options("Album", options).unwrap();
+    graph.set_family_options("Extra", options).unwrap();
+
+    let backup_dir = dir.path("backup");
+    graph.backup_to(&backup_dir).unwrap();
+
+    let restored = Graph::restore_from(&backup_dir, &dir.path("restored")).unwrap();
+    for family_name in ["Album", "Extra"] {
+        assert_eq!(restored.family_options(family_name).unwrap(), Some(options));
+    }
+}
+
+#[test]
+fn stats_summarises_counts_per_family() {
+    let dir = TestDir::new("stats");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+            1.0,
+        ))
+        .unwrap();
+
+    let stats = graph.stats().unwrap();
+    assert_eq!(stats.node_count, 2);
+    assert_eq!(stats.edge_count, 1);
+    assert_eq!(stats.family_counts["Song"], 1);
+    assert_eq!(stats.family_counts["Album"], 0);
+    assert!(stats.family_count >= families().len());
+    assert!(stats.to_string().starts_with("Nodes: 2\nEdges: 1\n"));
+}
+
+#[test]
+fn truncate_family_empties_only_that_family() {
+    let dir = TestDir::new("truncate-family");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    for i in 0..3 {
+        graph
+            .add_node(Song::new(None, format!("Track {}", i), vec![]))
+            .unwrap();
+    }
+    graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+
+    assert_eq!(graph.truncate_family("Song").unwrap(), 3);
+    assert_eq!(graph.count_nodes_in_family("Song").unwrap(), 0);
+    assert_eq!(graph.count_nodes_in_family("Artist").unwrap(), 1);
+    // The family is still there to write into.
+    graph
+        .add_node(Song::new(None, "Again".to_string(), vec![]))
+        .unwrap();
+    assert_eq!(graph.count_nodes_in_family("Song").unwrap(), 1);
+    assert!(matches!(
+        graph.truncate_family("Label"),
+        Err(GraphError::FindFamilyError)
+    ));
+}
+
+#[test]
+fn destroy_everything_drops_all_but_the_default_family() {
+    let dir = TestDir::new("destroy-everything");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+
+    graph.destroy_everything().unwrap();
+    assert_eq!(
+        graph.families_on_disk().unwrap(),
+        vec!["default".to_string()]
+    );
+}
+
+#[test]
+fn scan_raw_returns_undecoded_records() {
+    let dir = TestDir::new("scan-raw");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    for i in 0..3 {
+        graph
+            .add_node(Song::new(None, format!("Track {}", i), vec![]))
+            .unwrap();
+    }
+
+    let raw = graph.scan_raw("Song", 2).unwrap();
+    assert_eq!(raw.len(), 2);
+    assert!(raw[0].0.starts_with("Song:"));
+    assert!(preview_raw(&raw[0].1).contains("\"title\":\"Track "));
+    // Bytes that decode as neither format are shown as hex.
+    assert_eq!(preview_raw(&[0xc1, 0x0a]), "c1 0a");
+    assert!(matches!(
+        graph.scan_raw("Label", 1),
+        Err(GraphError::FindFamilyError)
+    ));
+}
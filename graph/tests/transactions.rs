@@ -0,0 +1,165 @@
+mod common;
+
+use common::TestDir;
+use graph::*;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn get_node_for_update_blocks_until_the_holder_commits() {
+    let dir = TestDir::new("get-node-for-update");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let album = graph
+        .add_node(Album::new(None, "Matter".to_string(), vec![], 2000))
+        .unwrap();
+    let album_id = album.id().to_string();
+
+    let (locked, wait_for_lock) = mpsc::channel();
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            let txn = graph.transaction();
+            let album: Album = graph.get_node_for_update(&txn, &album_id).unwrap();
+            locked.send(()).unwrap();
+            thread::sleep(Duration::from_millis(100));
+            let updated = Album::new(
+                Some(album_id.trim_start_matches("Album:").to_string()),
+                album.title().clone(),
+                vec![],
+                album.year() + 1,
+            );
+            txn.update_node(&updated).unwrap();
+            txn.commit().unwrap();
+        });
+
+        wait_for_lock.recv().unwrap();
+        let txn = graph.transaction();
+        let album: Album = graph.get_node_for_update(&txn, &album_id).unwrap();
+        // Only reachable once the first transaction has committed its write.
+        assert_eq!(*album.year(), 2001);
+        txn.commit().unwrap();
+    });
+}
+
+#[test]
+fn mutate_node_serializes_concurrent_writers() {
+    let dir = TestDir::new("mutate-node");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let song_id = graph
+        .add_node_get_id(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap()
+        .to_string();
+
+    thread::scope(|scope| {
+        for writer in 0..4 {
+            let (graph, song_id) = (&graph, &song_id);
+            scope.spawn(move || {
+                for i in 0..10 {
+                    let edge_id: ById =
+                        serde_json::from_value(format!("By:{}-{}", writer, i).into()).unwrap();
+                    graph
+                        .mutate_node::<Song, _>(song_id, |song| {
+                            song.add_out_edge_id(edge_id.into())
+                        })
+                        .unwrap();
+                }
+            });
+        }
+    });
+
+    // Every writer's additions survive; none was lost to a stale read.
+    let song: Song = graph.get_node(song_id).unwrap();
+    assert_eq!(song.out_edge_ids().len(), 40);
+}
+
+#[test]
+fn transaction_writes_land_together_or_not_at_all() {
+    let dir = TestDir::new("transaction");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+
+    let txn = graph.transaction();
+    let song = txn
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let artist = txn
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    txn.add_edge(By::new(
+        None,
+        ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+        1.0,
+    ))
+    .unwrap();
+    assert!(!graph.node_exists(&song.id().to_string()).unwrap());
+    txn.commit().unwrap();
+    assert_eq!(graph.out_degree(&song.id().to_string()).unwrap(), 1);
+
+    let txn = graph.transaction();
+    let discarded = txn
+        .add_node(Song::new(None, "Tides".to_string(), vec![]))
+        .unwrap();
+    txn.remove_node(&artist.id().to_string()).unwrap();
+    txn.rollback().unwrap();
+    assert!(!graph.node_exists(&discarded.id().to_string()).unwrap());
+    assert!(graph.node_exists(&artist.id().to_string()).unwrap());
+    assert_eq!(graph.count_nodes().unwrap(), 2);
+}
+
+#[test]
+fn transaction_add_edge_checks_endpoints_before_writing() {
+    let dir = TestDir::new("transaction-missing-endpoint");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+
+    let txn = graph.transaction();
+    let missing = Artist::new_id("missing".to_string());
+    assert!(matches!(
+        txn.add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song.id().clone(), missing),
+            1.0,
+        )),
+        Err(GraphError::FindKeyError)
+    ));
+    // Committing what is left of the transaction must not leave a dangling edge.
+    txn.commit().unwrap();
+    assert_eq!(graph.count_edges().unwrap(), 0);
+    assert_eq!(graph.out_degree(&song.id().to_string()).unwrap(), 0);
+}
+
+#[test]
+fn cloned_graphs_share_one_database_across_threads() {
+    let dir = TestDir::new("clone-graph");
+    let graph = Graph::builder(&dir.path("db"))
+        .node_cache(16)
+        .open()
+        .unwrap();
+    let handles: Vec<_> = (0..2)
+        .map(|i| {
+            let graph = graph.clone();
+            thread::spawn(move || {
+                graph
+                    .add_node(Song::new(
+                        Some(format!("t{}", i)),
+                        "Matter".to_string(),
+                        vec![],
+                    ))
+                    .unwrap()
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert!(graph.node_exists("Song:t0").unwrap());
+    assert!(graph.node_exists("Song:t1").unwrap());
+
+    // Clones share the node cache, so a removal through one is seen by all.
+    let other = graph.clone();
+    let _: Song = graph.get_node("Song:t0".to_string()).unwrap();
+    other.remove_node("Song:t0").unwrap();
+    assert!(graph.try_get_node::<Song>("Song:t0").unwrap().is_none());
+    assert!(matches!(graph.close(), Err(GraphError::DbNotClosed)));
+}
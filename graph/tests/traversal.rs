@@ -0,0 +1,546 @@
+mod common;
+
+use common::TestDir;
+use graph::*;
+
+#[test]
+fn export_subgraph_copies_nodes_and_edges_within_radius() {
+    let dir = TestDir::new("export-subgraph");
+    let graph = Graph::new(&dir.path("source")).unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    let album = graph
+        .add_node(Album::new(None, "Matter".to_string(), vec![], 2019))
+        .unwrap();
+    let playlist = graph.add_node(Playlist::new(None)).unwrap();
+    graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+            1.0,
+        ))
+        .unwrap();
+    graph
+        .add_edge(By::new(
+            None,
+            ByConnection::AlbumIsBy(album.id().clone(), artist.id().clone()),
+            1.0,
+        ))
+        .unwrap();
+    graph
+        .add_edge(Contains::new(
+            None,
+            ContainsConnection::PlaylistContainsSong(playlist.id().clone(), song.id().clone()),
+        ))
+        .unwrap();
+
+    graph
+        .export_subgraph(&[song.id().to_string()], 1, &dir.path("one-hop"))
+        .unwrap();
+    let one_hop = Graph::new(&dir.path("one-hop")).unwrap();
+    assert_eq!(one_hop.count_nodes().unwrap(), 3);
+    assert_eq!(one_hop.count_edges().unwrap(), 2);
+    assert!(!one_hop.node_exists(&album.id().to_string()).unwrap());
+    // The artist's edge to the album was left behind, so it must not dangle.
+    let exported_artist: Artist = one_hop.get_node(artist.id().to_string()).unwrap();
+    assert_eq!(exported_artist.in_edge_ids().len(), 1);
+
+    graph
+        .export_subgraph(&[song.id().to_string()], 0, &dir.path("seed-only"))
+        .unwrap();
+    let seed_only = Graph::new(&dir.path("seed-only")).unwrap();
+    assert_eq!(seed_only.count_nodes().unwrap(), 1);
+    assert_eq!(seed_only.count_edges().unwrap(), 0);
+}
+
+#[test]
+fn shortest_path_resolves_case_insensitive_ids() {
+    let dir = TestDir::new("shortest-path-case");
+    let graph = Graph::builder(&dir.path("db"))
+        .case_insensitive_families(true)
+        .open()
+        .unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+            1.0,
+        ))
+        .unwrap();
+
+    let from_id = song.id().to_string().replacen("Song", "song", 1);
+    let to_id = artist.id().to_string().replacen("Artist", "ARTIST", 1);
+    assert_eq!(
+        graph.shortest_path(&from_id, &to_id).unwrap(),
+        Some(vec![song.id().to_string(), artist.id().to_string()])
+    );
+    assert_eq!(
+        graph
+            .shortest_path(&from_id, &song.id().to_string())
+            .unwrap(),
+        Some(vec![song.id().to_string()])
+    );
+}
+
+#[test]
+fn join_yields_source_edge_and_target() {
+    let dir = TestDir::new("join");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    let album = graph
+        .add_node(Album::new(None, "Matter".to_string(), vec![], 2019))
+        .unwrap();
+    let edge = graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+            0.5,
+        ))
+        .unwrap();
+    // Same edge family, but from an Album, so not part of a Song join.
+    graph
+        .add_edge(By::new(
+            None,
+            ByConnection::AlbumIsBy(album.id().clone(), artist.id().clone()),
+            1.0,
+        ))
+        .unwrap();
+    // An edge whose target was never stored is skipped.
+    let dangling = format!(
+        r#"{{"id":"By:dangling","connection":{{"SongIsBy":["{}","Artist:missing"]}},"weight":2.0,"created_at":0}}"#,
+        song.id()
+    );
+    graph
+        .import_family_jsonl("By", dangling.as_bytes())
+        .unwrap();
+
+    let triples: Vec<(Song, By, Artist)> = graph
+        .join::<Song, By, Artist>()
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(triples.len(), 1);
+    let (source, joined_edge, target) = &triples[0];
+    assert_eq!(source.id(), song.id());
+    assert_eq!(joined_edge.id(), edge.id());
+    assert_eq!(*joined_edge.weight(), 0.5);
+    assert_eq!(target.id(), artist.id());
+}
+
+#[test]
+fn adjacency_cache_matches_live_neighbours_until_invalidated() {
+    let dir = TestDir::new("adjacency-cache");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let song_id = song.id().to_string();
+    let mut edge_ids = Vec::new();
+    for name in ["Family Stereo", "Other"] {
+        let artist = graph
+            .add_node(Artist::new(None, name.to_string(), None))
+            .unwrap();
+        let edge = graph
+            .add_edge(By::new(
+                None,
+                ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+                1.0,
+            ))
+            .unwrap();
+        edge_ids.push(edge.id().clone());
+    }
+
+    let mut live = graph.neighbours(&song_id).unwrap();
+    live.sort();
+    assert_eq!(live.len(), 2);
+    graph.build_adjacency_cache().unwrap();
+    let mut cached = graph.neighbours(&song_id).unwrap();
+    cached.sort();
+    assert_eq!(cached, live);
+
+    // The cache is a snapshot until it is rebuilt or invalidated.
+    graph.remove_edge::<By>(edge_ids[0].clone()).unwrap();
+    assert_eq!(graph.neighbours(&song_id).unwrap().len(), 2);
+    graph.invalidate_adjacency_cache().unwrap();
+    assert_eq!(graph.neighbours(&song_id).unwrap().len(), 1);
+    graph.build_adjacency_cache().unwrap();
+    assert_eq!(graph.neighbours(&song_id).unwrap().len(), 1);
+}
+
+fn collaborations(graph: &Graph, pairs: &[(usize, usize)], artist_count: usize) -> Vec<String> {
+    let artist_ids: Vec<ArtistId> = (0..artist_count)
+        .map(|i| {
+            graph
+                .add_node_get_id(Artist::new(
+                    Some(format!("{}", i)),
+                    format!("Artist {}", i),
+                    None,
+                ))
+                .unwrap()
+        })
+        .collect();
+    for (from, to) in pairs {
+        graph
+            .add_edge(CollaboratedWith::new(
+                None,
+                CollaboratedWithConnection::ArtistCollaboratedWith(
+                    artist_ids[*from].clone(),
+                    artist_ids[*to].clone(),
+                ),
+            ))
+            .unwrap();
+    }
+    artist_ids.iter().map(|id| id.to_string()).collect()
+}
+
+#[test]
+fn k_core_strips_low_degree_nodes() {
+    let dir = TestDir::new("k-core");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    // A triangle 0-1-2 with 3 hanging off 0: the 2-core is the triangle.
+    let ids = collaborations(&graph, &[(0, 1), (1, 2), (2, 0), (3, 0)], 4);
+
+    let mut two_core = graph.k_core::<CollaboratedWith, Artist>(2).unwrap();
+    two_core.sort();
+    assert_eq!(two_core, ids[..3].to_vec());
+    assert_eq!(
+        graph.k_core::<CollaboratedWith, Artist>(1).unwrap().len(),
+        4
+    );
+    assert!(graph
+        .k_core::<CollaboratedWith, Artist>(3)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn follow_collects_deduplicated_terminal_nodes() {
+    let dir = TestDir::new("follow");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    // Two routes to the same artist must not double up the albums.
+    for _ in 0..2 {
+        graph
+            .add_edge(By::new(
+                None,
+                ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+                1.0,
+            ))
+            .unwrap();
+    }
+    for (title, year) in [("Matter", 2019), ("Tides", 2021)] {
+        let album = graph
+            .add_node(Album::new(None, title.to_string(), vec![], year))
+            .unwrap();
+        graph
+            .add_edge(By::new(
+                None,
+                ByConnection::AlbumIsBy(album.id().clone(), artist.id().clone()),
+                1.0,
+            ))
+            .unwrap();
+    }
+
+    let albums: Vec<Album> = graph
+        .follow::<Song, Album>(&song.id().to_string(), "By", "By")
+        .unwrap();
+    let mut titles: Vec<&str> = albums.iter().map(|album| album.title().as_str()).collect();
+    titles.sort();
+    assert_eq!(titles, vec!["Matter", "Tides"]);
+}
+
+#[test]
+fn follow_resolves_case_insensitive_ids() {
+    let dir = TestDir::new("follow-case");
+    let graph = Graph::builder(&dir.path("db"))
+        .case_insensitive_families(true)
+        .open()
+        .unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    let album = graph
+        .add_node(Album::new(None, "Tides".to_string(), vec![], 2021))
+        .unwrap();
+    for connection in [
+        ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+        ByConnection::AlbumIsBy(album.id().clone(), artist.id().clone()),
+    ] {
+        graph.add_edge(By::new(None, connection, 1.0)).unwrap();
+    }
+
+    let start_id = song.id().to_string().replacen("Song", "song", 1);
+    let albums: Vec<Album> = graph.follow::<Song, Album>(&start_id, "By", "By").unwrap();
+    let album_ids: Vec<&AlbumId> = albums.iter().map(|album| album.id()).collect();
+    assert_eq!(album_ids, vec![album.id()]);
+}
+
+#[test]
+fn closeness_centrality_on_a_path() {
+    let dir = TestDir::new("closeness");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    // 0 - 1 - 2, with 3 on its own.
+    let ids = collaborations(&graph, &[(0, 1), (1, 2)], 4);
+
+    let centrality = graph
+        .closeness_centrality::<CollaboratedWith, Artist>()
+        .unwrap();
+    assert!((centrality[&ids[0]] - 2.0 / 3.0).abs() < 1e-9);
+    assert!((centrality[&ids[1]] - 1.0).abs() < 1e-9);
+    assert!((centrality[&ids[2]] - 2.0 / 3.0).abs() < 1e-9);
+    assert_eq!(centrality[&ids[3]], 0.0);
+}
+
+/// Playlist -> Song -> Artist, plus an Album with no edges.
+fn playlist_chain(graph: &Graph) -> (PlaylistId, SongId, ArtistId, AlbumId) {
+    let playlist = graph.add_node(Playlist::new(None)).unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    let album = graph
+        .add_node(Album::new(None, "Tides".to_string(), vec![], 2021))
+        .unwrap();
+    graph
+        .add_edge(Contains::new(
+            None,
+            ContainsConnection::PlaylistContainsSong(playlist.id().clone(), song.id().clone()),
+        ))
+        .unwrap();
+    graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+            1.0,
+        ))
+        .unwrap();
+    (
+        playlist.id().clone(),
+        song.id().clone(),
+        artist.id().clone(),
+        album.id().clone(),
+    )
+}
+
+#[test]
+fn get_neighbors_follows_out_edges_once_per_node() {
+    let dir = TestDir::new("get-neighbors");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let (_, song_id, artist_id, _) = playlist_chain(&graph);
+    graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song_id.clone(), artist_id.clone()),
+            0.5,
+        ))
+        .unwrap();
+
+    assert_eq!(
+        graph.get_neighbors::<Song>(&song_id.to_string()).unwrap(),
+        vec![artist_id.to_string()]
+    );
+    // Only out-edges are followed.
+    assert!(graph
+        .get_neighbors::<Artist>(&artist_id.to_string())
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn bfs_reports_depths_and_respects_the_limit() {
+    let dir = TestDir::new("bfs");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let (playlist_id, song_id, artist_id, _) = playlist_chain(&graph);
+    let start = playlist_id.to_string();
+
+    assert_eq!(
+        graph.bfs::<Playlist>(&start, 5).unwrap(),
+        vec![
+            (start.clone(), 0),
+            (song_id.to_string(), 1),
+            (artist_id.to_string(), 2),
+        ]
+    );
+    assert_eq!(graph.bfs::<Playlist>(&start, 1).unwrap().len(), 2);
+    assert_eq!(graph.bfs::<Playlist>(&start, 0).unwrap(), vec![(start, 0)]);
+    assert!(matches!(
+        graph.bfs::<Playlist>("Playlist:missing", 1),
+        Err(GraphError::FindKeyError)
+    ));
+}
+
+#[test]
+fn shortest_path_handles_reachable_unreachable_and_same_node() {
+    let dir = TestDir::new("shortest-path");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let (playlist_id, song_id, artist_id, album_id) = playlist_chain(&graph);
+    let (playlist, song, artist) = (
+        playlist_id.to_string(),
+        song_id.to_string(),
+        artist_id.to_string(),
+    );
+
+    assert_eq!(
+        graph.shortest_path(&playlist, &artist).unwrap(),
+        Some(vec![playlist.clone(), song, artist.clone()])
+    );
+    assert_eq!(
+        graph.shortest_path(&playlist, &playlist).unwrap(),
+        Some(vec![playlist.clone()])
+    );
+    assert_eq!(
+        graph
+            .shortest_path(&playlist, &album_id.to_string())
+            .unwrap(),
+        None
+    );
+    // Edges are followed in their own direction only.
+    assert_eq!(graph.shortest_path(&artist, &playlist).unwrap(), None);
+}
+
+#[test]
+fn snapshot_reads_ignore_later_writes() {
+    let dir = TestDir::new("snapshot");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let playlist = graph.add_node(Playlist::new(None)).unwrap();
+    let song = graph
+        .add_node(Song::new(None, "Matter".to_string(), vec![]))
+        .unwrap();
+    let artist = graph
+        .add_node(Artist::new(None, "Family Stereo".to_string(), None))
+        .unwrap();
+    graph
+        .add_edge(Contains::new(
+            None,
+            ContainsConnection::PlaylistContainsSong(playlist.id().clone(), song.id().clone()),
+        ))
+        .unwrap();
+
+    let snapshot = graph.snapshot();
+    graph
+        .add_edge(By::new(
+            None,
+            ByConnection::SongIsBy(song.id().clone(), artist.id().clone()),
+            1.0,
+        ))
+        .unwrap();
+
+    let (playlist_id, song_id) = (playlist.id().to_string(), song.id().to_string());
+    assert_eq!(snapshot.bfs::<Playlist>(&playlist_id, 5).unwrap().len(), 2);
+    assert_eq!(graph.bfs::<Playlist>(&playlist_id, 5).unwrap().len(), 3);
+    assert!(snapshot.get_neighbors::<Song>(&song_id).unwrap().is_empty());
+    assert_eq!(
+        snapshot
+            .shortest_path(&playlist_id, &artist.id().to_string())
+            .unwrap(),
+        None
+    );
+    let song: Song = snapshot.get_node(&song_id).unwrap();
+    assert!(song.out_edge_ids().is_empty());
+}
+
+#[test]
+fn connected_components_ignore_edge_direction() {
+    let dir = TestDir::new("connected-components");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    let (playlist_id, _, artist_id, album_id) = playlist_chain(&graph);
+    let other = graph
+        .add_node(Artist::new(None, "Other".to_string(), None))
+        .unwrap();
+    graph
+        .add_edge(By::new(
+            None,
+            ByConnection::AlbumIsBy(album_id.clone(), other.id().clone()),
+            1.0,
+        ))
+        .unwrap();
+
+    let components = graph.connected_components().unwrap();
+    assert_eq!(components.len(), 5);
+    let labels: std::collections::HashSet<_> = components.values().collect();
+    assert_eq!(labels.len(), 2);
+    let label = |id: String| components[&id];
+    assert_eq!(label(playlist_id.to_string()), label(artist_id.to_string()));
+    assert_eq!(label(album_id.to_string()), label(other.id().to_string()));
+    assert_ne!(label(playlist_id.to_string()), label(album_id.to_string()));
+}
+
+#[test]
+fn pagerank_matches_a_hand_computed_graph() {
+    let dir = TestDir::new("pagerank");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    // Playlist -> Song -> Artist <- Album
+    let (playlist_id, song_id, artist_id, album_id) = playlist_chain(&graph);
+    graph
+        .add_edge(By::new(
+            None,
+            ByConnection::AlbumIsBy(album_id.clone(), artist_id.clone()),
+            1.0,
+        ))
+        .unwrap();
+
+    let ranks = graph.pagerank(0.85, 100).unwrap();
+    // The artist is a sink, so its rank is spread evenly every round.
+    let base = 1.0 / 7.2725;
+    let close = |id: String, expected: f64| (ranks[&id] - expected).abs() < 1e-6;
+    assert!(close(playlist_id.to_string(), base));
+    assert!(close(album_id.to_string(), base));
+    assert!(close(song_id.to_string(), 1.85 * base));
+    assert!(close(artist_id.to_string(), 3.4225 * base));
+    assert!((ranks.values().sum::<f64>() - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn undirected_edges_are_traversed_both_ways() {
+    let dir = TestDir::new("undirected");
+    let graph = Graph::new(&dir.path("db")).unwrap();
+    // 0 - 1 - 2, added as 0 -> 1 and 2 -> 1.
+    let ids = collaborations(&graph, &[(0, 1), (2, 1)], 3);
+
+    let mut from_middle = graph.get_neighbors::<Artist>(&ids[1]).unwrap();
+    from_middle.sort();
+    let mut ends = vec![ids[0].clone(), ids[2].clone()];
+    ends.sort();
+    assert_eq!(from_middle, ends);
+    assert_eq!(
+        graph.get_neighbors::<Artist>(&ids[0]).unwrap(),
+        vec![ids[1].clone()]
+    );
+    let reached: Vec<String> = graph
+        .bfs::<Artist>(&ids[0], 3)
+        .unwrap()
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+    assert_eq!(reached.len(), 3);
+    assert!(reached.contains(&ids[2]));
+
+    let stored: Artist = graph.get_node(ids[0].clone()).unwrap();
+    assert_eq!(stored.in_edge_ids().len(), 1);
+    assert_eq!(stored.out_edge_ids().len(), 1);
+}